@@ -0,0 +1,93 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use linkedlist::{ArenaList, LinkedList};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+fn add_last(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_last");
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::new("LinkedList", size), &size, |b, &size| {
+            b.iter(|| {
+                let mut list = LinkedList::new();
+                for i in 0..size {
+                    list.add_last(black_box(i));
+                }
+                list
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("ArenaList", size), &size, |b, &size| {
+            b.iter(|| {
+                let mut list = ArenaList::new();
+                for i in 0..size {
+                    list.add_last(black_box(i));
+                }
+                list
+            })
+        });
+    }
+    group.finish();
+}
+
+fn iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iterate");
+    for size in SIZES {
+        let list: LinkedList<_> = (0..size).collect();
+        group.bench_with_input(BenchmarkId::new("LinkedList", size), &list, |b, list| {
+            b.iter(|| list.iter().sum::<usize>())
+        });
+
+        let list: ArenaList<_> = (0..size).collect();
+        group.bench_with_input(BenchmarkId::new("ArenaList", size), &list, |b, list| {
+            b.iter(|| list.iter().sum::<usize>())
+        });
+    }
+    group.finish();
+}
+
+/// Removes every other element and adds it back at the end, which is where a slab with free-list
+/// reuse should shine over allocating a fresh `Box` each time.
+fn churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("churn");
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::new("LinkedList", size), &size, |b, &size| {
+            b.iter_batched_ref(
+                || (0..size).collect::<LinkedList<_>>(),
+                |list| {
+                    let mut cursor = list.cursor_first_mut();
+                    let mut removed = Vec::with_capacity(size / 2);
+                    while cursor.current().is_some() {
+                        removed.extend(cursor.remove_current());
+                        cursor.move_next();
+                    }
+                    list.extend(removed);
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.bench_with_input(BenchmarkId::new("ArenaList", size), &size, |b, &size| {
+            b.iter_batched_ref(
+                || {
+                    let mut list = ArenaList::with_capacity(size);
+                    let handles: Vec<_> = (0..size).map(|i| list.add_last(i)).collect();
+                    (list, handles)
+                },
+                |(list, handles)| {
+                    let mut removed = Vec::with_capacity(size / 2);
+                    for handle in handles.iter().step_by(2) {
+                        removed.extend(list.remove(*handle));
+                    }
+                    list.extend(removed);
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_last, iterate, churn);
+criterion_main!(benches);
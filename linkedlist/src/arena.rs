@@ -0,0 +1,687 @@
+//! A doubly-linked list that keeps all of its nodes in a single `Vec` instead of allocating each
+//! one separately.
+//!
+//! Nodes link to each other by index, and removed nodes go on a free list so their slots get
+//! reused by later insertions. Adding an element hands out a `NodeHandle` that stays valid until
+//! that element is removed, which allows O(1) access, removal and insertion next to any element.
+
+use std::{fmt, iter::FusedIterator, marker::PhantomData, mem};
+
+/// A stable reference to an element of an `ArenaList`.
+///
+/// Handles carry a generation number, so a handle to a removed element is never mistaken for
+/// whatever element later reuses its slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeHandle {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Clone)]
+enum Slot<T> {
+    Occupied {
+        value: T,
+        prev: Option<usize>,
+        next: Option<usize>,
+    },
+    Free {
+        next_free: Option<usize>,
+    },
+}
+
+#[derive(Clone)]
+struct Entry<T> {
+    generation: u32,
+    slot: Slot<T>,
+}
+
+#[derive(Clone)]
+pub struct ArenaList<T> {
+    entries: Vec<Entry<T>>,
+    first: Option<usize>,
+    last: Option<usize>,
+    free: Option<usize>,
+    count: usize,
+}
+
+impl<T> ArenaList<T> {
+    pub fn new() -> ArenaList<T> {
+        ArenaList::with_capacity(0)
+    }
+
+    /// Creates a list with room for `capacity` elements before it has to reallocate.
+    pub fn with_capacity(capacity: usize) -> ArenaList<T> {
+        ArenaList {
+            entries: Vec::with_capacity(capacity),
+            first: None,
+            last: None,
+            free: None,
+            count: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn add_first(&mut self, value: T) -> NodeHandle {
+        self.link(value, None, self.first)
+    }
+
+    pub fn add_last(&mut self, value: T) -> NodeHandle {
+        self.link(value, self.last, None)
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        self.first.map(|index| self.unlink(index))
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.last.map(|index| self.unlink(index))
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.first.map(|index| self.value(index))
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.last.map(|index| self.value(index))
+    }
+
+    pub fn first_mut(&mut self) -> Option<&mut T> {
+        self.first.map(|index| self.value_mut(index))
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.last.map(|index| self.value_mut(index))
+    }
+
+    pub fn first_handle(&self) -> Option<NodeHandle> {
+        self.first.map(|index| self.handle(index))
+    }
+
+    pub fn last_handle(&self) -> Option<NodeHandle> {
+        self.last.map(|index| self.handle(index))
+    }
+
+    /// Returns whether `handle` refers to an element that's still in this list.
+    pub fn contains(&self, handle: NodeHandle) -> bool {
+        self.occupied(handle).is_some()
+    }
+
+    pub fn get(&self, handle: NodeHandle) -> Option<&T> {
+        self.occupied(handle).map(|index| self.value(index))
+    }
+
+    pub fn get_mut(&mut self, handle: NodeHandle) -> Option<&mut T> {
+        self.occupied(handle).map(|index| self.value_mut(index))
+    }
+
+    /// Returns the handle of the element after the one `handle` refers to.
+    pub fn next_handle(&self, handle: NodeHandle) -> Option<NodeHandle> {
+        let (_, next) = self.links(self.occupied(handle)?);
+        next.map(|index| self.handle(index))
+    }
+
+    /// Returns the handle of the element before the one `handle` refers to.
+    pub fn prev_handle(&self, handle: NodeHandle) -> Option<NodeHandle> {
+        let (prev, _) = self.links(self.occupied(handle)?);
+        prev.map(|index| self.handle(index))
+    }
+
+    /// Removes the element `handle` refers to and returns it, or `None` if it was already removed.
+    pub fn remove(&mut self, handle: NodeHandle) -> Option<T> {
+        self.occupied(handle).map(|index| self.unlink(index))
+    }
+
+    /// Inserts `value` right before the element `handle` refers to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` doesn't refer to an element of this list.
+    pub fn insert_before(&mut self, handle: NodeHandle, value: T) -> NodeHandle {
+        let index = self.expect_occupied(handle);
+        let (prev, _) = self.links(index);
+        self.link(value, prev, Some(index))
+    }
+
+    /// Inserts `value` right after the element `handle` refers to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` doesn't refer to an element of this list.
+    pub fn insert_after(&mut self, handle: NodeHandle, value: T) -> NodeHandle {
+        let index = self.expect_occupied(handle);
+        let (_, next) = self.links(index);
+        self.link(value, Some(index), next)
+    }
+
+    /// Removes every element. This invalidates all handles, but keeps the allocated memory.
+    pub fn clear(&mut self) {
+        // The slots are kept (all on the free list) instead of being truncated away, so their
+        // generations keep going up and old handles can't match whatever gets added next.
+        let len = self.entries.len();
+        self.first = None;
+        self.last = None;
+        self.free = if len == 0 { None } else { Some(0) };
+        self.count = 0;
+
+        for (index, entry) in self.entries.iter_mut().enumerate() {
+            if let Slot::Occupied { .. } = entry.slot {
+                entry.generation = entry.generation.wrapping_add(1);
+            }
+
+            let next_free = if index + 1 < len {
+                Some(index + 1)
+            } else {
+                None
+            };
+            entry.slot = Slot::Free { next_free };
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            list: self,
+            first: self.first,
+            last: self.last,
+            len: self.count,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            entries: self.entries.as_mut_ptr(),
+            first: self.first,
+            last: self.last,
+            len: self.count,
+            marker: PhantomData,
+        }
+    }
+
+    fn handle(&self, index: usize) -> NodeHandle {
+        NodeHandle {
+            index,
+            generation: self.entries[index].generation,
+        }
+    }
+
+    fn occupied(&self, handle: NodeHandle) -> Option<usize> {
+        let entry = self.entries.get(handle.index)?;
+        match entry.slot {
+            Slot::Occupied { .. } if entry.generation == handle.generation => Some(handle.index),
+            _ => None,
+        }
+    }
+
+    fn expect_occupied(&self, handle: NodeHandle) -> usize {
+        self.occupied(handle)
+            .expect("NodeHandle doesn't refer to an element of this list")
+    }
+
+    fn links(&self, index: usize) -> (Option<usize>, Option<usize>) {
+        match self.entries[index].slot {
+            Slot::Occupied { prev, next, .. } => (prev, next),
+            Slot::Free { .. } => unreachable!("linked to a free slot"),
+        }
+    }
+
+    fn value(&self, index: usize) -> &T {
+        match &self.entries[index].slot {
+            Slot::Occupied { value, .. } => value,
+            Slot::Free { .. } => unreachable!("linked to a free slot"),
+        }
+    }
+
+    fn value_mut(&mut self, index: usize) -> &mut T {
+        match &mut self.entries[index].slot {
+            Slot::Occupied { value, .. } => value,
+            Slot::Free { .. } => unreachable!("linked to a free slot"),
+        }
+    }
+
+    fn set_prev(&mut self, index: usize, new_prev: Option<usize>) {
+        if let Slot::Occupied { prev, .. } = &mut self.entries[index].slot {
+            *prev = new_prev;
+        }
+    }
+
+    fn set_next(&mut self, index: usize, new_next: Option<usize>) {
+        if let Slot::Occupied { next, .. } = &mut self.entries[index].slot {
+            *next = new_next;
+        }
+    }
+
+    /// Stores `value` in a free slot (reusing one if possible) and links it in between `prev` and
+    /// `next`, which must be adjacent.
+    fn link(&mut self, value: T, prev: Option<usize>, next: Option<usize>) -> NodeHandle {
+        let slot = Slot::Occupied { value, prev, next };
+        let index = match self.free {
+            Some(index) => {
+                let entry = &mut self.entries[index];
+                match mem::replace(&mut entry.slot, slot) {
+                    Slot::Free { next_free } => self.free = next_free,
+                    Slot::Occupied { .. } => unreachable!("free list points to an occupied slot"),
+                }
+                index
+            }
+            None => {
+                self.entries.push(Entry {
+                    generation: 0,
+                    slot,
+                });
+                self.entries.len() - 1
+            }
+        };
+
+        match prev {
+            Some(prev) => self.set_next(prev, Some(index)),
+            None => self.first = Some(index),
+        }
+
+        match next {
+            Some(next) => self.set_prev(next, Some(index)),
+            None => self.last = Some(index),
+        }
+
+        self.count += 1;
+        self.handle(index)
+    }
+
+    /// Unlinks the element at `index`, which must be occupied, and puts its slot on the free list.
+    fn unlink(&mut self, index: usize) -> T {
+        let entry = &mut self.entries[index];
+        let free = Slot::Free {
+            next_free: self.free,
+        };
+
+        let Slot::Occupied { value, prev, next } = mem::replace(&mut entry.slot, free) else {
+            unreachable!("unlinking a free slot");
+        };
+
+        entry.generation = entry.generation.wrapping_add(1);
+        self.free = Some(index);
+
+        match prev {
+            Some(prev) => self.set_next(prev, next),
+            None => self.first = next,
+        }
+
+        match next {
+            Some(next) => self.set_prev(next, prev),
+            None => self.last = prev,
+        }
+
+        self.count -= 1;
+        value
+    }
+}
+
+impl<T> Default for ArenaList<T> {
+    fn default() -> Self {
+        ArenaList::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for ArenaList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for ArenaList<T> {}
+
+impl<T> FromIterator<T> for ArenaList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = ArenaList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for ArenaList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.add_last(value);
+        }
+    }
+}
+
+pub struct Iter<'a, T> {
+    list: &'a ArenaList<T>,
+    first: Option<usize>,
+    last: Option<usize>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.first.map(|index| {
+            self.len -= 1;
+            self.first = self.list.links(index).1;
+            self.list.value(index)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.last.map(|index| {
+            self.len -= 1;
+            self.last = self.list.links(index).0;
+            self.list.value(index)
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a ArenaList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IterMut<'a, T> {
+    // A raw pointer rather than a `&mut [Entry<T>]`, since we hand out mutable references into
+    // the entries while still reading the links of the ones we haven't visited yet.
+    entries: *mut Entry<T>,
+    first: Option<usize>,
+    last: Option<usize>,
+    len: usize,
+    marker: PhantomData<&'a mut Entry<T>>,
+}
+
+// Safety: an `IterMut` behaves like a `&mut ArenaList<T>`.
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> IterMut<'a, T> {
+    /// Yields the value at `index` and returns its `(prev, next)` links.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be an occupied entry that hasn't been yielded yet.
+    unsafe fn take(&mut self, index: usize) -> (&'a mut T, Option<usize>, Option<usize>) {
+        // Safety: every linked index is in bounds, and `len` makes sure each entry is yielded at
+        // most once, so the mutable references never alias.
+        match unsafe { &mut (*self.entries.add(index)).slot } {
+            Slot::Occupied { value, prev, next } => (value, *prev, *next),
+            Slot::Free { .. } => unreachable!("linked to a free slot"),
+        }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let index = self.first?;
+        let (value, _, next) = unsafe { self.take(index) };
+        self.len -= 1;
+        self.first = next;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let index = self.last?;
+        let (value, prev, _) = unsafe { self.take(index) };
+        self.len -= 1;
+        self.last = prev;
+        Some(value)
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a mut ArenaList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+pub struct IntoIter<T> {
+    list: ArenaList<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.list.pop_first()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.count, Some(self.list.count))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.list.pop_last()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for ArenaList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect<T: Clone>(list: &ArenaList<T>) -> Vec<T> {
+        let forwards: Vec<_> = list.iter().cloned().collect();
+        let mut backwards: Vec<_> = list.iter().rev().cloned().collect();
+        backwards.reverse();
+        assert_eq!(forwards.len(), backwards.len());
+        assert_eq!(forwards.len(), list.count());
+        forwards
+    }
+
+    #[test]
+    fn works_like_a_deque() {
+        let mut list = ArenaList::new();
+        assert!(list.is_empty());
+        assert_eq!(list.first(), None);
+
+        list.add_last(2);
+        list.add_last(3);
+        list.add_first(1);
+        assert_eq!(collect(&list), vec![1, 2, 3]);
+        assert_eq!(list.first(), Some(&1));
+        assert_eq!(list.last(), Some(&3));
+
+        *list.first_mut().unwrap() = 10;
+        *list.last_mut().unwrap() = 30;
+        assert_eq!(list.pop_first(), Some(10));
+        assert_eq!(list.pop_last(), Some(30));
+        assert_eq!(list.pop_last(), Some(2));
+        assert_eq!(list.pop_first(), None);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn handles_give_o1_access() {
+        let mut list = ArenaList::new();
+        let a = list.add_last("a");
+        let c = list.add_last("c");
+        let b = list.insert_after(a, "b");
+        let z = list.insert_before(a, "z");
+        assert_eq!(collect(&list), vec!["z", "a", "b", "c"]);
+
+        assert_eq!(list.get(b), Some(&"b"));
+        *list.get_mut(c).unwrap() = "C";
+        assert_eq!(list.next_handle(a), Some(b));
+        assert_eq!(list.prev_handle(a), Some(z));
+        assert_eq!(list.prev_handle(z), None);
+        assert_eq!(list.first_handle(), Some(z));
+        assert_eq!(list.last_handle(), Some(c));
+
+        assert_eq!(list.remove(a), Some("a"));
+        assert_eq!(list.remove(a), None);
+        assert!(!list.contains(a));
+        assert_eq!(list.get(a), None);
+        assert_eq!(list.next_handle(z), Some(b));
+        assert_eq!(collect(&list), vec!["z", "b", "C"]);
+    }
+
+    #[test]
+    fn freed_slots_are_reused_without_reviving_old_handles() {
+        let mut list = ArenaList::new();
+        let a = list.add_last(1);
+        list.add_last(2);
+        list.remove(a);
+
+        let b = list.add_last(3);
+        assert_eq!(
+            list.entries.len(),
+            2,
+            "the freed slot should have been reused"
+        );
+        assert_ne!(a, b);
+        assert_eq!(list.get(a), None);
+        assert_eq!(list.get(b), Some(&3));
+        assert_eq!(collect(&list), vec![2, 3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn inserting_next_to_a_removed_element_panics() {
+        let mut list = ArenaList::new();
+        let a = list.add_last(1);
+        list.remove(a);
+        list.insert_after(a, 2);
+    }
+
+    #[test]
+    fn clear_invalidates_handles() {
+        let mut list: ArenaList<_> = (0..5).collect();
+        let handle = list.first_handle().unwrap();
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(list.get(handle), None);
+
+        let new_handle = list.add_last(7);
+        assert_eq!(list.get(handle), None);
+        assert_eq!(list.get(new_handle), Some(&7));
+    }
+
+    #[test]
+    fn iterators_from_both_ends() {
+        let mut list: ArenaList<_> = (1..=5).collect();
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&5));
+        assert_eq!(iter.len(), 3);
+
+        let mut iter_mut = list.iter_mut();
+        *iter_mut.next_back().unwrap() *= 10;
+        *iter_mut.next().unwrap() *= 100;
+        assert_eq!(iter_mut.len(), 3);
+        for value in iter_mut {
+            *value += 1;
+        }
+        assert_eq!(collect(&list), vec![100, 3, 4, 5, 50]);
+
+        let mut into_iter = list.into_iter();
+        assert_eq!(into_iter.next_back(), Some(50));
+        assert_eq!(into_iter.collect::<Vec<_>>(), vec![100, 3, 4, 5]);
+    }
+
+    #[test]
+    fn random_operations_match_a_vec() {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut rng = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        let mut list = ArenaList::new();
+        let mut expected: Vec<(NodeHandle, u64)> = Vec::new();
+        for step in 0..5000 {
+            let position = if expected.is_empty() {
+                0
+            } else {
+                rng() as usize % expected.len()
+            };
+
+            match rng() % 4 {
+                0 | 1 if !expected.is_empty() => {
+                    let (handle, value) = expected[position];
+                    let new = list.insert_after(handle, step);
+                    expected.insert(position + 1, (new, step));
+                    assert_eq!(list.get(handle), Some(&value));
+                }
+                2 if !expected.is_empty() => {
+                    let (handle, value) = expected.remove(position);
+                    assert_eq!(list.remove(handle), Some(value));
+                }
+                _ => expected.push((list.add_last(step), step)),
+            }
+        }
+
+        let values: Vec<_> = expected.iter().map(|(_, value)| *value).collect();
+        assert_eq!(collect(&list), values);
+    }
+}
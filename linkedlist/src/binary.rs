@@ -0,0 +1,363 @@
+//! A compact binary encoding for `LinkedList`s and the values inside them.
+//!
+//! A list is written as its element count followed by each element, one after the other. Lengths
+//! and integers are written as LEB128 varints (signed ones zigzag-encoded first), so small numbers
+//! only take a byte. Both directions work directly on the nodes: encoding walks the list and
+//! decoding appends each value as soon as it's read, without going through a `Vec`.
+
+use std::io::{self, Read, Write};
+
+use crate::LinkedList;
+
+pub trait Encode {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+pub trait Decode: Sized {
+    /// # Errors
+    ///
+    /// Fails with `UnexpectedEof` if the input ends too early, or with `InvalidData` if it doesn't
+    /// hold a valid encoding of `Self`.
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+
+    writer.write_all(&buf[..len])
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(reader)?;
+        let bits = (byte & 0x7f) as u64;
+        if shift == 63 && bits > 1 {
+            return Err(invalid_data("varint overflows 64 bits"));
+        }
+
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(invalid_data("varint is too long"))
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+macro_rules! impl_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl Encode for $ty {
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    write_varint(writer, *self as u64)
+                }
+            }
+
+            impl Decode for $ty {
+                fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                    <$ty>::try_from(read_varint(reader)?).map_err(|_| {
+                        invalid_data(concat!("value doesn't fit in a ", stringify!($ty)))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_signed {
+    ($($ty:ty),*) => {
+        $(
+            impl Encode for $ty {
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    write_varint(writer, zigzag(*self as i64))
+                }
+            }
+
+            impl Decode for $ty {
+                fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                    <$ty>::try_from(unzigzag(read_varint(reader)?)).map_err(|_| {
+                        invalid_data(concat!("value doesn't fit in a ", stringify!($ty)))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned!(u16, u32, u64, usize);
+impl_signed!(i16, i32, i64, isize);
+
+impl Encode for u8 {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[*self])
+    }
+}
+
+impl Decode for u8 {
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        read_byte(reader)
+    }
+}
+
+impl Encode for i8 {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+impl Decode for i8 {
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(read_byte(reader)? as i8)
+    }
+}
+
+impl Encode for bool {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[*self as u8])
+    }
+}
+
+impl Decode for bool {
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match read_byte(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("bool must be either 0 or 1")),
+        }
+    }
+}
+
+impl Encode for char {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, *self as u64)
+    }
+}
+
+impl Decode for char {
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        char::from_u32(u32::decode(reader)?).ok_or_else(|| invalid_data("not a valid char"))
+    }
+}
+
+impl Encode for f32 {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+impl Decode for f32 {
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes = [0u8; 4];
+        reader.read_exact(&mut bytes)?;
+        Ok(f32::from_le_bytes(bytes))
+    }
+}
+
+impl Encode for f64 {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+impl Decode for f64 {
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes))
+    }
+}
+
+impl Encode for str {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, self.len() as u64)?;
+        writer.write_all(self.as_bytes())
+    }
+}
+
+impl Encode for String {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.as_str().encode(writer)
+    }
+}
+
+impl Decode for String {
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = read_varint(reader)?;
+        // Read through `take` instead of allocating `len` bytes up front, so a corrupted length
+        // can't make us allocate more than the input actually has.
+        let mut bytes = Vec::new();
+        reader.take(len).read_to_end(&mut bytes)?;
+        if (bytes.len() as u64) < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        String::from_utf8(bytes).map_err(|_| invalid_data("string is not valid UTF-8"))
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (**self).encode(writer)
+    }
+}
+
+impl<T: Encode> Encode for LinkedList<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, self.count() as u64)?;
+        for value in self {
+            value.encode(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Decode> Decode for LinkedList<T> {
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let count = read_varint(reader)?;
+        let mut list = LinkedList::new();
+        for _ in 0..count {
+            list.add_last(T::decode(reader)?);
+        }
+
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linkedlist;
+
+    fn round_trip<T: Encode + Decode>(value: &T) -> T {
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes).unwrap();
+        let mut reader = bytes.as_slice();
+        let decoded = T::decode(&mut reader).unwrap();
+        assert!(reader.is_empty(), "decoding left bytes behind");
+        decoded
+    }
+
+    #[test]
+    fn varints_are_compact() {
+        let mut bytes = Vec::new();
+        linkedlist![0u32, 1, 127, 128, 300]
+            .encode(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, vec![5, 0, 1, 0x7f, 0x80, 0x01, 0xac, 0x02]);
+
+        let mut bytes = Vec::new();
+        linkedlist![0i32, -1, 1, -64].encode(&mut bytes).unwrap();
+        assert_eq!(bytes, vec![4, 0, 1, 2, 0x7f]);
+    }
+
+    #[test]
+    fn round_trips() {
+        let ints = linkedlist![i64::MIN, -1, 0, 1, i64::MAX];
+        assert_eq!(round_trip(&ints), ints);
+
+        let unsigned = linkedlist![0u64, u64::MAX, 1 << 35];
+        assert_eq!(round_trip(&unsigned), unsigned);
+
+        let strings = linkedlist![String::new(), String::from("héllo"), String::from("🦀")];
+        assert_eq!(round_trip(&strings), strings);
+
+        let mixed = linkedlist![
+            linkedlist![1.5f64, -0.0],
+            linkedlist![],
+            linkedlist![f64::MAX]
+        ];
+        assert_eq!(round_trip(&mixed), mixed);
+
+        let chars = linkedlist!['a', 'ß', '\u{10ffff}'];
+        assert_eq!(round_trip(&chars), chars);
+
+        let bools = linkedlist![true, false];
+        assert_eq!(round_trip(&bools), bools);
+
+        let empty: LinkedList<u8> = LinkedList::new();
+        assert_eq!(round_trip(&empty), empty);
+    }
+
+    #[test]
+    fn streams_several_lists_back_to_back() {
+        let mut bytes = Vec::new();
+        linkedlist![1u8, 2].encode(&mut bytes).unwrap();
+        linkedlist![3u8].encode(&mut bytes).unwrap();
+
+        let mut reader = bytes.as_slice();
+        assert_eq!(
+            LinkedList::<u8>::decode(&mut reader).unwrap(),
+            linkedlist![1, 2]
+        );
+        assert_eq!(
+            LinkedList::<u8>::decode(&mut reader).unwrap(),
+            linkedlist![3]
+        );
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let mut bytes = Vec::new();
+        linkedlist![String::from("abc"), String::from("def")]
+            .encode(&mut bytes)
+            .unwrap();
+
+        for len in 0..bytes.len() {
+            let error = LinkedList::<String>::decode(&mut &bytes[..len]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn invalid_input_is_an_error() {
+        let overlong = [0xffu8; 11];
+        let error = u64::decode(&mut &overlong[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = u16::decode(&mut &[0x80, 0x80, 0x04][..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = bool::decode(&mut &[2][..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = String::decode(&mut &[2, 0xff, 0xfe][..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = char::decode(&mut &[0x80, 0xb0, 0x03][..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,362 @@
+//! A multi-producer multi-consumer FIFO queue that can be shared between threads.
+//!
+//! This is the "two-lock" queue from Michael and Scott's paper: producers only take the tail lock
+//! and consumers only take the head lock, so pushing and popping don't contend with each other.
+//! The queue always keeps one dummy node at its head, which is what lets both ends be modified
+//! independently even when the queue is empty or has a single element.
+
+use std::{
+    ptr::{self, NonNull},
+    sync::{
+        atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+        Condvar, Mutex, MutexGuard, PoisonError,
+    },
+};
+
+struct QueueNode<T> {
+    value: Option<T>,
+    // Written by producers (holding the tail lock) and read by consumers (holding the head lock),
+    // so this one field has to be atomic.
+    next: AtomicPtr<QueueNode<T>>,
+}
+
+impl<T> QueueNode<T> {
+    fn new(value: Option<T>) -> NonNull<QueueNode<T>> {
+        let node = Box::new(QueueNode {
+            value,
+            next: AtomicPtr::new(ptr::null_mut()),
+        });
+
+        NonNull::from(Box::leak(node))
+    }
+}
+
+pub struct Queue<T> {
+    head: Mutex<NonNull<QueueNode<T>>>,
+    tail: Mutex<NonNull<QueueNode<T>>>,
+    not_empty: Condvar,
+    waiting: AtomicUsize,
+    closed: AtomicBool,
+}
+
+// Safety: the nodes are only ever touched while holding the matching lock (or through the atomic
+// `next` pointer), and values only move between threads by being pushed and popped.
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+impl<T> Queue<T> {
+    pub fn new() -> Queue<T> {
+        let dummy = QueueNode::new(None);
+        Queue {
+            head: Mutex::new(dummy),
+            tail: Mutex::new(dummy),
+            not_empty: Condvar::new(),
+            waiting: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Adds `value` to the back of the queue, waking up a blocked `pop` if there is one.
+    ///
+    /// # Errors
+    ///
+    /// If the queue was closed, `value` is handed back.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        {
+            let mut tail = self.tail.lock().unwrap();
+            if self.closed.load(Ordering::SeqCst) {
+                return Err(value);
+            }
+
+            let node = QueueNode::new(Some(value));
+            // Safety: the tail node stays alive while we hold the tail lock, since consumers never
+            // free the node the tail points to (it's never behind the dummy). We only touch its
+            // `next` field, which consumers may be reading at the same time.
+            unsafe { (*tail.as_ptr()).next.store(node.as_ptr(), Ordering::SeqCst) };
+            *tail = node;
+        }
+
+        // A consumer announces itself in `waiting` before checking for nodes, and we check
+        // `waiting` after adding ours, so at least one of us sees the other. Taking the head lock
+        // makes sure that consumer is already inside `wait` before we notify it.
+        if self.waiting.load(Ordering::SeqCst) > 0 {
+            drop(self.head.lock().unwrap());
+            self.not_empty.notify_one();
+        }
+
+        Ok(())
+    }
+
+    /// Takes the value at the front of the queue, or returns `None` right away if it's empty.
+    pub fn try_pop(&self) -> Option<T> {
+        let mut head = self.head.lock().unwrap();
+        Self::pop_locked(&mut head)
+    }
+
+    /// Takes the value at the front of the queue, waiting for one to be pushed if it's empty.
+    /// Returns `None` once the queue is closed and there is nothing left in it.
+    pub fn pop(&self) -> Option<T> {
+        let mut head = self.head.lock().unwrap();
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+
+        let result = loop {
+            if let Some(value) = Self::pop_locked(&mut head) {
+                break Some(value);
+            }
+
+            if self.closed.load(Ordering::SeqCst) {
+                break None;
+            }
+
+            head = self.not_empty.wait(head).unwrap();
+        };
+
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    /// Closes the queue: every later `push` fails, and once the values already in the queue are
+    /// popped, `pop` returns `None` instead of blocking.
+    pub fn close(&self) {
+        {
+            let _tail = self.tail.lock().unwrap();
+            self.closed.store(true, Ordering::SeqCst);
+        }
+
+        drop(self.head.lock().unwrap());
+        self.not_empty.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    fn pop_locked(head: &mut MutexGuard<NonNull<QueueNode<T>>>) -> Option<T> {
+        let dummy = **head;
+
+        // Safety: the dummy is owned by whoever holds the head lock, which is us. The node after it
+        // is fully initialized before being published through `next`, and producers only ever
+        // touch its `next` field, never its value.
+        unsafe {
+            let next = NonNull::new((*dummy.as_ptr()).next.load(Ordering::SeqCst))?;
+            let value = (*next.as_ptr()).value.take();
+            **head = next;
+            drop(Box::from_raw(dummy.as_ptr()));
+            value
+        }
+    }
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        // A thread that panicked while holding the lock can't have left the list half-updated, so
+        // the nodes still have to be freed.
+        let mut current = Some(*self.head.get_mut().unwrap_or_else(PoisonError::into_inner));
+        while let Some(node) = current {
+            // Safety: we have exclusive access, and every node is freed exactly once.
+            let node = unsafe { Box::from_raw(node.as_ptr()) };
+            current = NonNull::new(node.next.load(Ordering::Relaxed));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Barrier},
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn single_thread_fifo() {
+        let queue = Queue::new();
+        assert_eq!(queue.try_pop(), None);
+
+        for i in 0..5 {
+            queue.push(i).unwrap();
+        }
+
+        assert_eq!(queue.try_pop(), Some(0));
+        assert_eq!(queue.pop(), Some(1));
+        queue.push(5).unwrap();
+
+        let rest: Vec<_> = std::iter::from_fn(|| queue.try_pop()).collect();
+        assert_eq!(rest, vec![2, 3, 4, 5]);
+        assert_eq!(queue.try_pop(), None);
+    }
+
+    #[test]
+    fn close_rejects_pushes_and_drains() {
+        let queue = Queue::new();
+        queue.push(String::from("a")).unwrap();
+        queue.push(String::from("b")).unwrap();
+        queue.close();
+
+        assert!(queue.is_closed());
+        assert_eq!(queue.push(String::from("c")), Err(String::from("c")));
+        assert_eq!(queue.pop().as_deref(), Some("a"));
+        assert_eq!(queue.pop().as_deref(), Some("b"));
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.try_pop(), None);
+    }
+
+    #[test]
+    fn dropping_frees_remaining_values() {
+        let tracker = Arc::new(());
+        let queue = Queue::new();
+        for _ in 0..100 {
+            queue.push(Arc::clone(&tracker)).unwrap();
+        }
+
+        queue.try_pop();
+        assert_eq!(Arc::strong_count(&tracker), 100);
+        drop(queue);
+        assert_eq!(Arc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn dropping_after_a_panic_frees_remaining_values() {
+        let tracker = Arc::new(());
+        let queue = Queue::new();
+        queue.push(Arc::clone(&tracker)).unwrap();
+
+        thread::scope(|s| {
+            let shared = &queue;
+            s.spawn(move || {
+                let _head = shared.head.lock().unwrap();
+                panic!("poisoning the head lock");
+            })
+            .join()
+            .unwrap_err();
+        });
+
+        assert!(queue.head.is_poisoned());
+        drop(queue);
+        assert_eq!(Arc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn blocking_pop_wakes_up_on_push() {
+        let queue = Arc::new(Queue::new());
+        let consumer = {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || queue.pop())
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        queue.push(42).unwrap();
+        assert_eq!(consumer.join().unwrap(), Some(42));
+    }
+
+    #[test]
+    fn close_wakes_up_every_blocked_pop() {
+        let queue: Arc<Queue<i32>> = Arc::new(Queue::new());
+        let consumers: Vec<_> = (0..4)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || queue.pop())
+            })
+            .collect();
+
+        thread::sleep(Duration::from_millis(50));
+        queue.close();
+        for consumer in consumers {
+            assert_eq!(consumer.join().unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn many_producers_many_consumers() {
+        const PRODUCERS: usize = 4;
+        const CONSUMERS: usize = 4;
+        const PER_PRODUCER: usize = 20_000;
+
+        let queue = Queue::new();
+        let start = Barrier::new(PRODUCERS + CONSUMERS);
+
+        let received: Vec<Vec<(usize, usize)>> = thread::scope(|scope| {
+            let consumers: Vec<_> = (0..CONSUMERS)
+                .map(|_| {
+                    scope.spawn(|| {
+                        start.wait();
+                        std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            let producers: Vec<_> = (0..PRODUCERS)
+                .map(|producer| {
+                    let (queue, start) = (&queue, &start);
+                    scope.spawn(move || {
+                        start.wait();
+                        for i in 0..PER_PRODUCER {
+                            queue.push((producer, i)).unwrap();
+                        }
+                    })
+                })
+                .collect();
+
+            for producer in producers {
+                producer.join().unwrap();
+            }
+
+            queue.close();
+            consumers.into_iter().map(|c| c.join().unwrap()).collect()
+        });
+
+        // Each consumer must see every producer's values in the order they were pushed.
+        for values in &received {
+            let mut last_seen = [None; PRODUCERS];
+            for &(producer, i) in values {
+                assert!(last_seen[producer] < Some(i));
+                last_seen[producer] = Some(i);
+            }
+        }
+
+        // And every value must have been received exactly once.
+        let mut all: Vec<_> = received.into_iter().flatten().collect();
+        all.sort();
+        let expected: Vec<_> = (0..PRODUCERS)
+            .flat_map(|producer| (0..PER_PRODUCER).map(move |i| (producer, i)))
+            .collect();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn try_pop_under_contention() {
+        const THREADS: usize = 4;
+        const PER_THREAD: usize = 20_000;
+
+        let queue = Queue::new();
+        let total: usize = thread::scope(|scope| {
+            let workers: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut sum = 0;
+                        for i in 0..PER_THREAD {
+                            queue.push(i).unwrap();
+                            if let Some(value) = queue.try_pop() {
+                                sum += value;
+                            }
+                        }
+                        sum
+                    })
+                })
+                .collect();
+
+            workers.into_iter().map(|w| w.join().unwrap()).sum()
+        });
+
+        let leftover: usize = std::iter::from_fn(|| queue.try_pop()).sum();
+        assert_eq!(total + leftover, THREADS * (0..PER_THREAD).sum::<usize>());
+    }
+}
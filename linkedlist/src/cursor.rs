@@ -0,0 +1,472 @@
+use std::ptr::NonNull;
+
+use crate::{LinkedList, LinkedListNode};
+
+/// A read-only cursor over a `LinkedList`.
+///
+/// A cursor points either at an element or at the "ghost" position, which sits between the last
+/// and the first element. Moving past either end of the list lands on the ghost, and moving again
+/// wraps around to the other end.
+pub struct Cursor<'a, T> {
+    index: usize,
+    current: Option<NonNull<LinkedListNode<T>>>,
+    list: &'a LinkedList<T>,
+}
+
+impl<'a, T> Cursor<'a, T> {
+    pub(crate) fn new(
+        list: &'a LinkedList<T>,
+        current: Option<NonNull<LinkedListNode<T>>>,
+        index: usize,
+    ) -> Cursor<'a, T> {
+        Cursor {
+            index,
+            current,
+            list,
+        }
+    }
+
+    /// Returns the index of the element the cursor is pointing at, or `None` if it's on the ghost.
+    pub fn index(&self) -> Option<usize> {
+        self.current.map(|_| self.index)
+    }
+
+    pub fn move_next(&mut self) {
+        match self.current.take() {
+            // Safety: `current` always points to a live node owned by `list`.
+            Some(current) => unsafe {
+                self.current = (*current.as_ptr()).next;
+                self.index += 1;
+            },
+            None => {
+                self.current = self.list.first;
+                self.index = 0;
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match self.current.take() {
+            Some(current) => unsafe {
+                self.current = (*current.as_ptr()).prev;
+                self.index = self.index.checked_sub(1).unwrap_or(self.list.count);
+            },
+            None => {
+                self.current = self.list.last;
+                self.index = self.list.count.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn current(&self) -> Option<&'a T> {
+        self.current.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn peek_next(&self) -> Option<&'a T> {
+        let next = match self.current {
+            Some(current) => unsafe { (*current.as_ptr()).next },
+            None => self.list.first,
+        };
+
+        next.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        let prev = match self.current {
+            Some(current) => unsafe { (*current.as_ptr()).prev },
+            None => self.list.last,
+        };
+
+        prev.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn as_list(&self) -> &'a LinkedList<T> {
+        self.list
+    }
+}
+
+impl<T> Clone for Cursor<'_, T> {
+    fn clone(&self) -> Self {
+        Cursor { ..*self }
+    }
+}
+
+/// A cursor over a `LinkedList` that can insert and remove elements around its position.
+///
+/// See `Cursor` for how the cursor moves. Every mutation keeps the list's `count` and the cursor's
+/// index in sync.
+pub struct CursorMut<'a, T> {
+    index: usize,
+    current: Option<NonNull<LinkedListNode<T>>>,
+    list: &'a mut LinkedList<T>,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub(crate) fn new(
+        list: &'a mut LinkedList<T>,
+        current: Option<NonNull<LinkedListNode<T>>>,
+        index: usize,
+    ) -> CursorMut<'a, T> {
+        CursorMut {
+            index,
+            current,
+            list,
+        }
+    }
+
+    pub fn index(&self) -> Option<usize> {
+        self.current.map(|_| self.index)
+    }
+
+    pub fn move_next(&mut self) {
+        match self.current.take() {
+            Some(current) => unsafe {
+                self.current = (*current.as_ptr()).next;
+                self.index += 1;
+            },
+            None => {
+                self.current = self.list.first;
+                self.index = 0;
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match self.current.take() {
+            Some(current) => unsafe {
+                self.current = (*current.as_ptr()).prev;
+                self.index = self.index.checked_sub(1).unwrap_or(self.list.count);
+            },
+            None => {
+                self.current = self.list.last;
+                self.index = self.list.count.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.current
+            .map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = match self.current {
+            Some(current) => unsafe { (*current.as_ptr()).next },
+            None => self.list.first,
+        };
+
+        next.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        let prev = match self.current {
+            Some(current) => unsafe { (*current.as_ptr()).prev },
+            None => self.list.last,
+        };
+
+        prev.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// Returns a read-only cursor at the same position, borrowing this one.
+    pub fn as_cursor(&self) -> Cursor<'_, T> {
+        Cursor::new(self.list, self.current, self.index)
+    }
+
+    pub fn as_list(&self) -> &LinkedList<T> {
+        self.list
+    }
+
+    /// Inserts `value` right after the current element. If the cursor is on the ghost, `value`
+    /// becomes the new first element.
+    pub fn insert_after(&mut self, value: T) {
+        let node = NonNull::from(Box::leak(Box::new(LinkedListNode::new(value))));
+        let next = match self.current {
+            Some(current) => unsafe { (*current.as_ptr()).next },
+            None => self.list.first,
+        };
+
+        // Safety: `current` and `next` are adjacent, and `node` is a fresh unlinked node.
+        unsafe { self.list.splice_nodes(self.current, next, node, node, 1) };
+        if self.current.is_none() {
+            self.index = self.list.count;
+        }
+    }
+
+    /// Inserts `value` right before the current element. If the cursor is on the ghost, `value`
+    /// becomes the new last element.
+    pub fn insert_before(&mut self, value: T) {
+        let node = NonNull::from(Box::leak(Box::new(LinkedListNode::new(value))));
+        let prev = match self.current {
+            Some(current) => unsafe { (*current.as_ptr()).prev },
+            None => self.list.last,
+        };
+
+        unsafe { self.list.splice_nodes(prev, self.current, node, node, 1) };
+        self.index += 1;
+    }
+
+    /// Removes the current element and returns it, moving the cursor to the next element (or to
+    /// the ghost if it was the last one). Does nothing if the cursor is on the ghost.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.current?;
+
+        // Safety: `node` is owned by the list; once unlinked we take back the `Box` it came from.
+        unsafe {
+            self.current = (*node.as_ptr()).next;
+            self.list.unlink_node(node);
+            Some(Box::from_raw(node.as_ptr()).value)
+        }
+    }
+
+    /// Splits the list after the current element, returning everything after it. If the cursor is
+    /// on the ghost, the whole list is returned.
+    pub fn split_after(&mut self) -> LinkedList<T> {
+        let at = if self.current.is_some() {
+            self.index + 1
+        } else {
+            self.index = 0;
+            0
+        };
+
+        unsafe { self.list.split_off_after_node(self.current, at) }
+    }
+
+    /// Splits the list before the current element, returning everything before it. If the cursor
+    /// is on the ghost, the whole list is returned.
+    pub fn split_before(&mut self) -> LinkedList<T> {
+        let at = self.index;
+        self.index = 0;
+        unsafe { self.list.split_off_before_node(self.current, at) }
+    }
+
+    /// Moves all the elements of `list` in between the current element and the previous one. If
+    /// the cursor is on the ghost, they're added at the end.
+    pub fn splice_before(&mut self, mut list: LinkedList<T>) {
+        if let Some((first, last, len)) = list.detach_all_nodes() {
+            let prev = match self.current {
+                Some(current) => unsafe { (*current.as_ptr()).prev },
+                None => self.list.last,
+            };
+
+            unsafe { self.list.splice_nodes(prev, self.current, first, last, len) };
+            self.index += len;
+        }
+    }
+
+    /// Moves all the elements of `list` in between the current element and the next one. If the
+    /// cursor is on the ghost, they're added at the start.
+    pub fn splice_after(&mut self, mut list: LinkedList<T>) {
+        if let Some((first, last, len)) = list.detach_all_nodes() {
+            let next = match self.current {
+                Some(current) => unsafe { (*current.as_ptr()).next },
+                None => self.list.first,
+            };
+
+            unsafe { self.list.splice_nodes(self.current, next, first, last, len) };
+            if self.current.is_none() {
+                self.index = self.list.count;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LinkedList;
+
+    fn list_of(values: &[i32]) -> LinkedList<i32> {
+        let mut list = LinkedList::new();
+        for value in values {
+            list.add_last(*value);
+        }
+        list
+    }
+
+    fn collect(list: &LinkedList<i32>) -> Vec<i32> {
+        list.iter().copied().collect()
+    }
+
+    fn collect_rev(list: &LinkedList<i32>) -> Vec<i32> {
+        let mut values: Vec<_> = list.iter().rev().copied().collect();
+        values.reverse();
+        values
+    }
+
+    #[test]
+    fn cursor_walks_and_wraps() {
+        let list = list_of(&[1, 2, 3]);
+        let mut cursor = list.cursor_first();
+        assert_eq!(cursor.current(), Some(&1));
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.peek_prev(), None);
+        assert_eq!(cursor.peek_next(), Some(&2));
+
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&3));
+        assert_eq!(cursor.index(), Some(2));
+
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.peek_next(), Some(&1));
+        assert_eq!(cursor.peek_prev(), Some(&3));
+
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&1));
+
+        cursor.move_prev();
+        assert_eq!(cursor.current(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&3));
+        assert_eq!(cursor.index(), Some(2));
+    }
+
+    #[test]
+    fn cursor_on_empty_list() {
+        let mut list: LinkedList<i32> = LinkedList::new();
+        let mut cursor = list.cursor_last();
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        cursor.move_prev();
+        assert_eq!(cursor.current(), None);
+
+        let mut cursor = list.cursor_first_mut();
+        assert_eq!(cursor.remove_current(), None);
+        cursor.insert_after(2);
+        cursor.insert_before(3);
+        cursor.insert_after(1);
+        assert_eq!(collect(&list), vec![1, 2, 3]);
+        assert_eq!(list.count(), 3);
+    }
+
+    #[test]
+    fn cursor_mut_inserts() {
+        let mut list = list_of(&[1, 3]);
+        let mut cursor = list.cursor_first_mut();
+        cursor.insert_after(2);
+        assert_eq!(cursor.index(), Some(0));
+        cursor.insert_before(0);
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(cursor.current(), Some(&mut 1));
+
+        cursor.move_next();
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+        cursor.insert_before(4);
+        cursor.insert_after(-1);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut -1));
+        assert_eq!(cursor.index(), Some(0));
+
+        assert_eq!(collect(&list), vec![-1, 0, 1, 2, 3, 4]);
+        assert_eq!(collect_rev(&list), vec![-1, 0, 1, 2, 3, 4]);
+        assert_eq!(list.count(), 6);
+    }
+
+    #[test]
+    fn cursor_mut_removes_while_walking() {
+        let mut list = list_of(&[1, 2, 3, 4, 5, 6]);
+        let mut cursor = list.cursor_first_mut();
+        while let Some(value) = cursor.current() {
+            if *value % 2 == 0 {
+                cursor.remove_current();
+            } else {
+                *value *= 10;
+                cursor.move_next();
+            }
+        }
+
+        assert_eq!(collect(&list), vec![10, 30, 50]);
+        assert_eq!(collect_rev(&list), vec![10, 30, 50]);
+        assert_eq!(list.count(), 3);
+
+        let mut cursor = list.cursor_last_mut();
+        assert_eq!(cursor.remove_current(), Some(50));
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(list.last(), Some(&30));
+        assert_eq!(list.count(), 2);
+    }
+
+    #[test]
+    fn cursor_mut_splits() {
+        let mut list = list_of(&[1, 2, 3, 4, 5]);
+        let mut cursor = list.cursor_first_mut();
+        cursor.move_next();
+        cursor.move_next();
+
+        let after = cursor.split_after();
+        assert_eq!(cursor.index(), Some(2));
+        let before = cursor.split_before();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.current(), Some(&mut 3));
+
+        assert_eq!(collect(&before), vec![1, 2]);
+        assert_eq!(collect_rev(&before), vec![1, 2]);
+        assert_eq!(before.count(), 2);
+        assert_eq!(collect(&after), vec![4, 5]);
+        assert_eq!(collect_rev(&after), vec![4, 5]);
+        assert_eq!(after.count(), 2);
+        assert_eq!(collect(&list), vec![3]);
+        assert_eq!(list.count(), 1);
+
+        let mut cursor = list.cursor_first_mut();
+        assert_eq!(cursor.split_after().count(), 0);
+        assert_eq!(cursor.split_before().count(), 0);
+        cursor.move_next();
+        let everything = cursor.split_after();
+        assert_eq!(collect(&everything), vec![3]);
+        assert_eq!(list.count(), 0);
+        assert_eq!(list.first(), None);
+        assert_eq!(list.last(), None);
+    }
+
+    #[test]
+    fn cursor_mut_splices() {
+        let mut list = list_of(&[1, 5]);
+        let mut cursor = list.cursor_first_mut();
+        cursor.splice_after(list_of(&[2, 3]));
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_next();
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 5));
+        cursor.splice_before(list_of(&[4]));
+        assert_eq!(cursor.index(), Some(4));
+        cursor.splice_before(LinkedList::new());
+        cursor.splice_after(LinkedList::new());
+
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+        cursor.splice_before(list_of(&[6, 7]));
+        cursor.splice_after(list_of(&[-1, 0]));
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+
+        assert_eq!(collect(&list), vec![-1, 0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(collect_rev(&list), vec![-1, 0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(list.count(), 9);
+    }
+
+    #[test]
+    fn cursor_mut_keeps_ownership_straight() {
+        let mut list = LinkedList::new();
+        for i in 0..4 {
+            list.add_last(i.to_string());
+        }
+
+        let mut cursor = list.cursor_first_mut();
+        cursor.move_next();
+        assert_eq!(cursor.remove_current().as_deref(), Some("1"));
+        cursor.insert_before("x".to_string());
+        let tail = cursor.split_after();
+        drop(tail);
+
+        let values: Vec<_> = list.iter().map(String::as_str).collect();
+        assert_eq!(values, vec!["0", "x", "2"]);
+    }
+}
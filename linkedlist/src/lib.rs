@@ -1,18 +1,71 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem,
+    ptr::NonNull,
+};
+
+pub mod arena;
+pub mod binary;
+pub mod concurrent;
+mod cursor;
+pub mod persistent;
+#[cfg(feature = "serde")]
+mod serialization;
+mod sort;
+
+pub use arena::{ArenaList, NodeHandle};
+pub use cursor::{Cursor, CursorMut};
+
 pub struct LinkedListNode<T> {
     value: T,
-    next: Option<Box<LinkedListNode<T>>>,
+    next: Option<NonNull<LinkedListNode<T>>>,
+    prev: Option<NonNull<LinkedListNode<T>>>,
 }
 
+/// A run of detached nodes: its first node, its last node and how many nodes it has.
+type DetachedNodes<T> = (
+    NonNull<LinkedListNode<T>>,
+    NonNull<LinkedListNode<T>>,
+    usize,
+);
+
+/// A doubly-linked list. Nodes are heap-allocated individually and owned by the list, which keeps
+/// pointers to both ends so that adding and removing at either end is O(1).
 pub struct LinkedList<T> {
-    first: Option<Box<LinkedListNode<T>>>,
+    first: Option<NonNull<LinkedListNode<T>>>,
+    last: Option<NonNull<LinkedListNode<T>>>,
     count: usize,
+    // Tells the compiler we own `LinkedListNode<T>`s (and therefore `T`s), even though we only
+    // hold raw pointers to them.
+    marker: PhantomData<Box<LinkedListNode<T>>>,
+}
+
+// Safety: the list owns its nodes exclusively, so sending or sharing it is exactly as safe as
+// sending or sharing the `T`s inside.
+unsafe impl<T: Send> Send for LinkedList<T> {}
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+
+impl<T> LinkedListNode<T> {
+    fn new(value: T) -> LinkedListNode<T> {
+        LinkedListNode {
+            value,
+            next: None,
+            prev: None,
+        }
+    }
 }
 
 impl<T> LinkedList<T> {
     pub fn new() -> LinkedList<T> {
         LinkedList {
             first: None,
+            last: None,
             count: 0,
+            marker: PhantomData,
         }
     }
 
@@ -20,38 +73,442 @@ impl<T> LinkedList<T> {
         self.count
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
     pub fn add_first(&mut self, value: T) {
-        self.first = Some(Box::new(LinkedListNode {
-            value,
-            next: self.first.take(),
-        }));
-        self.count += 1;
+        let node = Box::new(LinkedListNode::new(value));
+        self.push_first_node(node);
+    }
+
+    pub fn add_last(&mut self, value: T) {
+        let node = Box::new(LinkedListNode::new(value));
+        self.push_last_node(node);
     }
 
     pub fn pop_first(&mut self) -> Option<T> {
-        if let Some(mut node) = self.first.take() {
-            self.first = node.next.take();
-            self.count -= 1;
-            return Some(node.value);
+        self.pop_first_node().map(|node| node.value)
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.pop_last_node().map(|node| node.value)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        // Safety: every node pointer in the list points to a live node owned by the list.
+        self.first.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.last.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn first_mut(&mut self) -> Option<&mut T> {
+        self.first
+            .map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.last.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            first: self.first,
+            last: self.last,
+            len: self.count,
+            marker: PhantomData,
         }
+    }
 
-        None
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            first: self.first,
+            last: self.last,
+            len: self.count,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a cursor positioned at the first element, or at the "ghost" position if the list
+    /// is empty.
+    pub fn cursor_first(&self) -> Cursor<'_, T> {
+        Cursor::new(self, self.first, 0)
+    }
+
+    /// Returns a cursor positioned at the last element, or at the "ghost" position if the list
+    /// is empty.
+    pub fn cursor_last(&self) -> Cursor<'_, T> {
+        Cursor::new(self, self.last, self.count.saturating_sub(1))
+    }
+
+    pub fn cursor_first_mut(&mut self) -> CursorMut<'_, T> {
+        let first = self.first;
+        CursorMut::new(self, first, 0)
+    }
+
+    pub fn cursor_last_mut(&mut self) -> CursorMut<'_, T> {
+        let (last, index) = (self.last, self.count.saturating_sub(1));
+        CursorMut::new(self, last, index)
+    }
+
+    pub fn contains(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.iter().any(|x| x == value)
+    }
+
+    /// Removes all the elements from the list.
+    pub fn clear(&mut self) {
+        drop(mem::take(self));
+    }
+
+    /// Moves all the elements of `other` to the end of this list, leaving `other` empty. This is
+    /// O(1), no matter how long either list is.
+    pub fn append(&mut self, other: &mut LinkedList<T>) {
+        if let Some((first, last, len)) = other.detach_all_nodes() {
+            // Safety: the end of the list is always a valid place to splice, and the nodes we
+            // just detached from `other` aren't owned by any list anymore.
+            unsafe { self.splice_nodes(self.last, None, first, last, len) };
+        }
     }
 
-    pub fn iter(&self) -> Iter<T> {
-        if let Some(first) = &self.first {
-            return Iter::new(Some(first));
+    /// Splits the list in two at the given index, returning everything from `at` onwards. Walks
+    /// from whichever end of the list is closer to `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > count`.
+    pub fn split_off(&mut self, at: usize) -> LinkedList<T> {
+        assert!(at <= self.count, "Cannot split off at a nonexistent index");
+        if at == 0 {
+            return mem::take(self);
         }
 
-        Iter::new(None)
+        let node = self.node_at(at - 1);
+        // Safety: `node` was just found at index `at - 1` of this list.
+        unsafe { self.split_off_after_node(Some(node), at) }
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<T> {
-        if let Some(first) = &mut self.first {
-            return IterMut::new(Some(first));
+    /// Removes the element at the given index and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= count`.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.count,
+            "Cannot remove at index {index}, the list only has {} elements",
+            self.count
+        );
+
+        let node = self.node_at(index);
+        // Safety: `node` is owned by this list; once unlinked we take back its `Box`.
+        unsafe {
+            self.unlink_node(node);
+            Box::from_raw(node.as_ptr()).value
         }
+    }
 
-        IterMut::new(None)
+    /// Inserts `value` at the given index, shifting everything after it one position back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > count`.
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(
+            index <= self.count,
+            "Cannot insert at index {index}, the list only has {} elements",
+            self.count
+        );
+
+        if index == self.count {
+            self.add_last(value);
+            return;
+        }
+
+        let next = self.node_at(index);
+        let node = NonNull::from(Box::leak(Box::new(LinkedListNode::new(value))));
+        // Safety: `next` belongs to this list and its `prev` is, by definition, adjacent to it.
+        unsafe {
+            let prev = (*next.as_ptr()).prev;
+            self.splice_nodes(prev, Some(next), node, node, 1);
+        }
+    }
+
+    /// Shortens the list to `len` elements, dropping the rest. Does nothing if the list isn't
+    /// longer than that.
+    pub fn truncate(&mut self, len: usize) {
+        while self.count > len {
+            self.pop_last_node();
+        }
+    }
+
+    /// Reverses the order of the elements in place, by relinking the nodes.
+    pub fn reverse(&mut self) {
+        let mut current = self.first;
+        while let Some(node) = current {
+            // Safety: `node` is owned by the list, and we only swap its own pointers.
+            unsafe {
+                let node = &mut *node.as_ptr();
+                mem::swap(&mut node.next, &mut node.prev);
+                current = node.prev;
+            }
+        }
+
+        mem::swap(&mut self.first, &mut self.last);
+    }
+
+    /// Keeps only the elements for which `f` returns `true`, in their original order.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain_mut(|value| f(value));
+    }
+
+    /// Like `retain`, but `f` can also modify the elements it keeps.
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let mut cursor = self.cursor_first_mut();
+        while let Some(value) = cursor.current() {
+            if f(value) {
+                cursor.move_next();
+            } else {
+                cursor.remove_current();
+            }
+        }
+    }
+
+    /// Returns an iterator that removes and yields the elements for which `filter` returns `true`.
+    /// Elements are only visited as the iterator advances, so if it's dropped early whatever it
+    /// hasn't reached yet stays in the list.
+    pub fn extract_if<F>(&mut self, filter: F) -> ExtractIf<'_, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        ExtractIf {
+            cursor: self.cursor_first_mut(),
+            filter,
+        }
+    }
+
+    /// Finds the node at the given index, walking from whichever end is closer.
+    fn node_at(&self, index: usize) -> NonNull<LinkedListNode<T>> {
+        debug_assert!(index < self.count);
+
+        // Safety: `index < count`, so we never walk off either end of the list.
+        unsafe {
+            if index <= self.count / 2 {
+                let mut node = self.first.unwrap_unchecked();
+                for _ in 0..index {
+                    node = (*node.as_ptr()).next.unwrap_unchecked();
+                }
+                node
+            } else {
+                let mut node = self.last.unwrap_unchecked();
+                for _ in index + 1..self.count {
+                    node = (*node.as_ptr()).prev.unwrap_unchecked();
+                }
+                node
+            }
+        }
+    }
+
+    fn push_first_node(&mut self, mut node: Box<LinkedListNode<T>>) {
+        node.next = self.first;
+        node.prev = None;
+        let node = NonNull::from(Box::leak(node));
+
+        match self.first {
+            // Safety: `first` points to a live node owned by the list.
+            Some(first) => unsafe { (*first.as_ptr()).prev = Some(node) },
+            None => self.last = Some(node),
+        }
+
+        self.first = Some(node);
+        self.count += 1;
+    }
+
+    fn push_last_node(&mut self, mut node: Box<LinkedListNode<T>>) {
+        node.next = None;
+        node.prev = self.last;
+        let node = NonNull::from(Box::leak(node));
+
+        match self.last {
+            // Safety: `last` points to a live node owned by the list.
+            Some(last) => unsafe { (*last.as_ptr()).next = Some(node) },
+            None => self.first = Some(node),
+        }
+
+        self.last = Some(node);
+        self.count += 1;
+    }
+
+    fn pop_first_node(&mut self) -> Option<Box<LinkedListNode<T>>> {
+        self.first.map(|node| {
+            // Safety: the node was allocated with `Box` in `push_*_node` and is being unlinked
+            // here, so we're taking back ownership of it exactly once.
+            let node = unsafe { Box::from_raw(node.as_ptr()) };
+            self.first = node.next;
+
+            match self.first {
+                Some(first) => unsafe { (*first.as_ptr()).prev = None },
+                None => self.last = None,
+            }
+
+            self.count -= 1;
+            node
+        })
+    }
+
+    fn pop_last_node(&mut self) -> Option<Box<LinkedListNode<T>>> {
+        self.last.map(|node| {
+            // Safety: see `pop_first_node`.
+            let node = unsafe { Box::from_raw(node.as_ptr()) };
+            self.last = node.prev;
+
+            match self.last {
+                Some(last) => unsafe { (*last.as_ptr()).next = None },
+                None => self.first = None,
+            }
+
+            self.count -= 1;
+            node
+        })
+    }
+
+    /// Unlinks `node` from the list without deallocating it.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a node currently owned by this list.
+    unsafe fn unlink_node(&mut self, node: NonNull<LinkedListNode<T>>) {
+        let node = unsafe { &mut *node.as_ptr() };
+
+        match node.prev {
+            Some(prev) => unsafe { (*prev.as_ptr()).next = node.next },
+            None => self.first = node.next,
+        }
+
+        match node.next {
+            Some(next) => unsafe { (*next.as_ptr()).prev = node.prev },
+            None => self.last = node.prev,
+        }
+
+        node.next = None;
+        node.prev = None;
+        self.count -= 1;
+    }
+
+    /// Links the chain of `len` nodes going from `first` to `last` in between `prev` and `next`,
+    /// where `None` stands for the start or the end of the list respectively.
+    ///
+    /// # Safety
+    ///
+    /// `prev` and `next` must be adjacent nodes of this list (or the matching end of it), and the
+    /// chain must be a properly linked run of `len` nodes not owned by any list.
+    unsafe fn splice_nodes(
+        &mut self,
+        prev: Option<NonNull<LinkedListNode<T>>>,
+        next: Option<NonNull<LinkedListNode<T>>>,
+        first: NonNull<LinkedListNode<T>>,
+        last: NonNull<LinkedListNode<T>>,
+        len: usize,
+    ) {
+        match prev {
+            Some(prev) => unsafe { (*prev.as_ptr()).next = Some(first) },
+            None => self.first = Some(first),
+        }
+
+        match next {
+            Some(next) => unsafe { (*next.as_ptr()).prev = Some(last) },
+            None => self.last = Some(last),
+        }
+
+        unsafe {
+            (*first.as_ptr()).prev = prev;
+            (*last.as_ptr()).next = next;
+        }
+
+        self.count += len;
+    }
+
+    /// Takes every node out of the list, returning the first and last nodes and how many there
+    /// were, or `None` if the list was empty. The list is left empty.
+    fn detach_all_nodes(&mut self) -> Option<DetachedNodes<T>> {
+        let first = self.first.take()?;
+        let last = self.last.take()?;
+        let len = mem::replace(&mut self.count, 0);
+        Some((first, last, len))
+    }
+
+    /// Splits the list in two right after `node`, which sits at index `at - 1`, returning
+    /// everything after it. If `node` is `None` the whole list is returned.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a node owned by this list and `at` must be its index plus one.
+    unsafe fn split_off_after_node(
+        &mut self,
+        node: Option<NonNull<LinkedListNode<T>>>,
+        at: usize,
+    ) -> LinkedList<T> {
+        let Some(node) = node else {
+            return mem::take(self);
+        };
+
+        let Some(second_first) = (unsafe { (*node.as_ptr()).next.take() }) else {
+            return LinkedList::new();
+        };
+
+        unsafe { (*second_first.as_ptr()).prev = None };
+        let second = LinkedList {
+            first: Some(second_first),
+            last: self.last,
+            count: self.count - at,
+            marker: PhantomData,
+        };
+
+        self.last = Some(node);
+        self.count = at;
+        second
+    }
+
+    /// Splits the list in two right before `node`, which sits at index `at`, returning everything
+    /// before it. If `node` is `None` the whole list is returned.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a node owned by this list and `at` must be its index.
+    unsafe fn split_off_before_node(
+        &mut self,
+        node: Option<NonNull<LinkedListNode<T>>>,
+        at: usize,
+    ) -> LinkedList<T> {
+        let Some(node) = node else {
+            return mem::take(self);
+        };
+
+        let Some(first_last) = (unsafe { (*node.as_ptr()).prev.take() }) else {
+            return LinkedList::new();
+        };
+
+        unsafe { (*first_last.as_ptr()).next = None };
+        let first = LinkedList {
+            first: self.first,
+            last: Some(first_last),
+            count: at,
+            marker: PhantomData,
+        };
+
+        self.first = Some(node);
+        self.count -= at;
+        first
     }
 }
 
@@ -61,123 +518,312 @@ impl<T> Default for LinkedList<T> {
     }
 }
 
-pub struct Iter<'a, T> {
-    next: Option<&'a LinkedListNode<T>>,
+impl<T> Drop for LinkedList<T> {
+    fn drop(&mut self) {
+        // Nodes are freed one at a time in a loop rather than recursively through their `next`
+        // pointers, so dropping a list with millions of elements can't overflow the stack.
+        struct DropGuard<'a, T>(&'a mut LinkedList<T>);
+
+        impl<T> Drop for DropGuard<'_, T> {
+            fn drop(&mut self) {
+                // Only runs if dropping one of the values panicked: keep freeing the rest of the
+                // nodes so a single bad `Drop` doesn't leak the whole list.
+                while self.0.pop_first_node().is_some() {}
+            }
+        }
+
+        let guard = DropGuard(self);
+        while guard.0.pop_first_node().is_some() {}
+        mem::forget(guard);
+    }
 }
 
-impl<'a, T> Iter<'a, T> {
-    pub fn new(first_node: Option<&'a LinkedListNode<T>>) -> Iter<T> {
-        Iter { next: first_node }
+impl<T: Clone> Clone for LinkedList<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
     }
 }
 
+impl<T: fmt::Debug> fmt::Debug for LinkedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for LinkedList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for LinkedList<T> {}
+
+impl<T: PartialOrd> PartialOrd for LinkedList<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other)
+    }
+}
+
+impl<T: Ord> Ord for LinkedList<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other)
+    }
+}
+
+impl<T: Hash> Hash for LinkedList<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hashing the length first keeps lists of lists from colliding when the same elements are
+        // just split up differently, same as slices do.
+        state.write_usize(self.count);
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for LinkedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = LinkedList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for LinkedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.add_last(value);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a> Extend<&'a T> for LinkedList<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for LinkedList<T> {
+    fn from(array: [T; N]) -> Self {
+        array.into_iter().collect()
+    }
+}
+
+impl<T> From<Vec<T>> for LinkedList<T> {
+    fn from(vec: Vec<T>) -> Self {
+        vec.into_iter().collect()
+    }
+}
+
+pub struct Iter<'a, T> {
+    first: Option<NonNull<LinkedListNode<T>>>,
+    last: Option<NonNull<LinkedListNode<T>>>,
+    len: usize,
+    marker: PhantomData<&'a LinkedListNode<T>>,
+}
+
+// Safety: an `Iter` only hands out shared references, so it behaves like a `&LinkedList<T>`.
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
 impl<'a, T> Iterator for Iter<'a, T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<&'a T> {
-        if let Some(node) = self.next.take() {
-            if let Some(next) = &node.next {
-                self.next = Some(next);
-            }
+        if self.len == 0 {
+            return None;
+        }
+
+        self.first.map(|node| {
+            // Safety: `len` guarantees we haven't walked past `last`, so the node is still alive
+            // and borrowed from the list for `'a`.
+            let node = unsafe { &*node.as_ptr() };
+            self.len -= 1;
+            self.first = node.next;
+            &node.value
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
 
-            return Some(&node.value);
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
         }
 
-        None
+        self.last.map(|node| {
+            let node = unsafe { &*node.as_ptr() };
+            self.len -= 1;
+            self.last = node.prev;
+            &node.value
+        })
     }
 }
 
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
 impl<'a, T> IntoIterator for &'a LinkedList<T> {
     type Item = &'a T;
     type IntoIter = Iter<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        return self.iter();
+        self.iter()
     }
 }
 
 pub struct IterMut<'a, T> {
-    next: Option<&'a mut LinkedListNode<T>>,
+    first: Option<NonNull<LinkedListNode<T>>>,
+    last: Option<NonNull<LinkedListNode<T>>>,
+    len: usize,
+    marker: PhantomData<&'a mut LinkedListNode<T>>,
 }
 
-impl<'a, T> IterMut<'a, T> {
-    pub fn new(first_node: Option<&'a mut LinkedListNode<T>>) -> IterMut<T> {
-        IterMut { next: first_node }
-    }
-}
+// Safety: an `IterMut` behaves like a `&mut LinkedList<T>`.
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
 
 impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
 
     fn next(&mut self) -> Option<&'a mut T> {
-        if let Some(node) = self.next.take() {
-            if let Some(next) = &mut node.next {
-                self.next = Some(next);
-            }
+        if self.len == 0 {
+            return None;
+        }
 
-            return Some(&mut node.value);
+        self.first.map(|node| {
+            // Safety: every node is yielded at most once (from either end, as tracked by `len`),
+            // so the mutable references we hand out never alias.
+            let node = unsafe { &mut *node.as_ptr() };
+            self.len -= 1;
+            self.first = node.next;
+            &mut node.value
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
         }
 
-        None
+        self.last.map(|node| {
+            let node = unsafe { &mut *node.as_ptr() };
+            self.len -= 1;
+            self.last = node.prev;
+            &mut node.value
+        })
     }
 }
 
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
 impl<'a, T> IntoIterator for &'a mut LinkedList<T> {
     type Item = &'a mut T;
     type IntoIter = IterMut<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        return self.iter_mut();
+        self.iter_mut()
     }
 }
 
+/// An owning iterator over a `LinkedList`. Dropping it half-consumed frees the remaining nodes
+/// through `LinkedList`'s (non-recursive) `Drop`.
 pub struct IntoIter<T> {
-    next: Option<LinkedListNode<T>>,
-}
-
-impl<T> IntoIter<T> {
-    pub fn new(first_node: Option<LinkedListNode<T>>) -> IntoIter<T> {
-        IntoIter { next: first_node }
-    }
+    list: LinkedList<T>,
 }
 
 impl<T> Iterator for IntoIter<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<T> {
-        if let Some(node) = self.next.take() {
-            if let Some(next) = node.next {
-                self.next = Some(*next);
-            }
+        self.list.pop_first()
+    }
 
-            return Some(node.value);
-        }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.count, Some(self.list.count))
+    }
+}
 
-        None
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.list.pop_last()
     }
 }
 
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
 impl<T> IntoIterator for LinkedList<T> {
     type Item = T;
     type IntoIter = IntoIter<T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        IntoIter::new(self.first.map(|x| *x))
+        IntoIter { list: self }
+    }
+}
+
+/// The iterator returned by `LinkedList::extract_if`.
+pub struct ExtractIf<'a, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    cursor: CursorMut<'a, T>,
+    filter: F,
+}
+
+impl<T, F> Iterator for ExtractIf<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while let Some(value) = self.cursor.current() {
+            if (self.filter)(value) {
+                return self.cursor.remove_current();
+            }
+
+            self.cursor.move_next();
+        }
+
+        None
     }
 }
 
-/*#[macro_export]
+/// Creates a `LinkedList` containing the given elements, in order.
+///
+/// ```
+/// let list = linkedlist::linkedlist![1, 2, 3];
+/// assert_eq!(list.first(), Some(&1));
+/// assert_eq!(list.last(), Some(&3));
+/// ```
+#[macro_export]
 macro_rules! linkedlist {
-    ( $( $x:expr ),* ) => {
+    ( $( $x:expr ),* $(,)? ) => {
         {
-            let mut temp_list = LinkedList::new();
+            #[allow(unused_mut)]
+            let mut temp_list = $crate::LinkedList::new();
             $(
                 temp_list.add_last($x);
             )*
             temp_list
         }
     };
-}*/
+}
 
 #[cfg(test)]
 mod tests {
@@ -306,4 +952,437 @@ mod tests {
             assert_eq!(index as i32 + 1, item);
         }
     }
+
+    #[test]
+    fn add_last_and_pop_last() {
+        let mut list = LinkedList::new();
+        list.add_last(1);
+        list.add_last(2);
+        list.add_first(0);
+        assert_eq!(list.count(), 3);
+
+        let collected: Vec<_> = list.iter().copied().collect();
+        assert_eq!(collected, vec![0, 1, 2]);
+
+        assert_eq!(list.pop_last(), Some(2));
+        assert_eq!(list.pop_last(), Some(1));
+        assert_eq!(list.count(), 1);
+        assert_eq!(list.pop_last(), Some(0));
+        assert_eq!(list.pop_last(), None);
+        assert_eq!(list.pop_first(), None);
+        assert_eq!(list.count(), 0);
+
+        // The list must still be usable from both ends after being emptied.
+        list.add_last(5);
+        assert_eq!(list.pop_first(), Some(5));
+        list.add_first(6);
+        assert_eq!(list.pop_last(), Some(6));
+        assert_eq!(list.iter().next(), None);
+    }
+
+    #[test]
+    fn first_and_last() {
+        let mut list = LinkedList::new();
+        assert_eq!(list.first(), None);
+        assert_eq!(list.last(), None);
+        assert_eq!(list.first_mut(), None);
+        assert_eq!(list.last_mut(), None);
+
+        list.add_last(1);
+        assert_eq!(list.first(), Some(&1));
+        assert_eq!(list.last(), Some(&1));
+
+        list.add_last(2);
+        list.add_last(3);
+        *list.first_mut().unwrap() *= 10;
+        *list.last_mut().unwrap() *= 100;
+        assert_eq!(list.first(), Some(&10));
+        assert_eq!(list.last(), Some(&300));
+    }
+
+    #[test]
+    fn iter_from_both_ends() {
+        let mut list = LinkedList::new();
+        for i in 1..=5 {
+            list.add_last(i);
+        }
+
+        let reversed: Vec<_> = list.iter().rev().copied().collect();
+        assert_eq!(reversed, vec![5, 4, 3, 2, 1]);
+
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&5));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.next_back(), Some(&3));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn iter_mut_from_both_ends() {
+        let mut list = LinkedList::new();
+        for i in 1..=4 {
+            list.add_last(i);
+        }
+
+        let mut iter = list.iter_mut();
+        *iter.next_back().unwrap() = 40;
+        *iter.next().unwrap() = 10;
+        *iter.next_back().unwrap() = 30;
+        *iter.next().unwrap() = 20;
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let collected: Vec<_> = list.iter().copied().collect();
+        assert_eq!(collected, vec![10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn iter_into_from_both_ends() {
+        let mut list = LinkedList::new();
+        for i in 1..=4 {
+            list.add_last(i.to_string());
+        }
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.next_back().as_deref(), Some("4"));
+        assert_eq!(iter.next().as_deref(), Some("1"));
+        assert_eq!(iter.next_back().as_deref(), Some("3"));
+        assert_eq!(iter.next().as_deref(), Some("2"));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn linkedlist_macro() {
+        let empty: LinkedList<i32> = linkedlist![];
+        assert_eq!(empty.count(), 0);
+
+        let list = linkedlist![1, 2, 3,];
+        assert_eq!(list.count(), 3);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn clone_and_eq() {
+        let list = linkedlist![String::from("a"), String::from("b")];
+        let mut cloned = list.clone();
+        assert_eq!(list, cloned);
+
+        cloned.add_last(String::from("c"));
+        assert_ne!(list, cloned);
+        cloned.pop_last();
+        *cloned.first_mut().unwrap() = String::from("z");
+        assert_ne!(list, cloned);
+        assert_eq!(list.first().map(String::as_str), Some("a"));
+    }
+
+    #[test]
+    fn debug_prints_like_a_list() {
+        assert_eq!(format!("{:?}", linkedlist![1, 2, 3]), "[1, 2, 3]");
+        assert_eq!(format!("{:?}", LinkedList::<i32>::new()), "[]");
+    }
+
+    #[test]
+    fn ordering_is_lexicographic() {
+        assert!(linkedlist![1, 2] < linkedlist![1, 3]);
+        assert!(linkedlist![1, 2] < linkedlist![1, 2, 0]);
+        assert!(linkedlist![2] > linkedlist![1, 9, 9]);
+        assert_eq!(linkedlist![1, 2].cmp(&linkedlist![1, 2]), Ordering::Equal);
+        assert_eq!(linkedlist![f64::NAN].partial_cmp(&linkedlist![1.0]), None);
+    }
+
+    #[test]
+    fn hash_matches_for_equal_lists() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash_of<T: Hash>(value: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        assert_eq!(
+            hash_of(&linkedlist![1, 2, 3]),
+            hash_of(&linkedlist![1, 2, 3])
+        );
+        let nested_a = linkedlist![linkedlist![1], linkedlist![2, 3]];
+        let nested_b = linkedlist![linkedlist![1, 2], linkedlist![3]];
+        assert_ne!(hash_of(&nested_a), hash_of(&nested_b));
+    }
+
+    #[test]
+    fn collect_extend_and_from() {
+        let mut list: LinkedList<_> = (1..=3).collect();
+        list.extend(vec![4, 5]);
+        list.extend(&[6, 7]);
+        assert_eq!(list, LinkedList::from([1, 2, 3, 4, 5, 6, 7]));
+        assert_eq!(list.count(), 7);
+        assert_eq!(LinkedList::from(vec![1, 2]), linkedlist![1, 2]);
+    }
+
+    #[test]
+    fn iterators_know_their_length() {
+        let mut list = linkedlist![1, 2, 3, 4];
+
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 4);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        iter.next();
+        iter.next();
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+
+        let mut iter_mut = list.iter_mut();
+        iter_mut.next_back();
+        assert_eq!(iter_mut.len(), 3);
+
+        let mut into_iter = list.into_iter();
+        into_iter.next();
+        assert_eq!(into_iter.len(), 3);
+        assert_eq!(into_iter.rev().collect::<Vec<_>>(), vec![4, 3, 2]);
+    }
+
+    const HUGE_COUNT: usize = 10_000_000;
+
+    #[test]
+    fn dropping_a_huge_list_does_not_overflow() {
+        let mut list = LinkedList::new();
+        for i in 0..HUGE_COUNT {
+            list.add_last(i);
+        }
+
+        assert_eq!(list.count(), HUGE_COUNT);
+        drop(list);
+    }
+
+    #[test]
+    fn dropping_a_half_consumed_huge_into_iter_does_not_overflow() {
+        let list: LinkedList<_> = (0..HUGE_COUNT).collect();
+        let mut iter = list.into_iter();
+        for _ in 0..HUGE_COUNT / 2 {
+            iter.next();
+        }
+
+        assert_eq!(iter.len(), HUGE_COUNT - HUGE_COUNT / 2);
+        drop(iter);
+    }
+
+    #[test]
+    fn drop_keeps_going_after_a_panic() {
+        use std::{
+            panic::{self, AssertUnwindSafe},
+            rc::Rc,
+        };
+
+        struct PanicOnDrop {
+            panics: bool,
+            _tracker: Rc<()>,
+        }
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                if self.panics {
+                    panic!("PanicOnDrop was dropped");
+                }
+            }
+        }
+
+        let tracker = Rc::new(());
+        let list = linkedlist![
+            PanicOnDrop {
+                panics: false,
+                _tracker: Rc::clone(&tracker),
+            },
+            PanicOnDrop {
+                panics: true,
+                _tracker: Rc::clone(&tracker),
+            },
+            PanicOnDrop {
+                panics: false,
+                _tracker: Rc::clone(&tracker),
+            },
+            PanicOnDrop {
+                panics: false,
+                _tracker: Rc::clone(&tracker),
+            },
+        ];
+        assert_eq!(Rc::strong_count(&tracker), 5);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(move || drop(list)));
+        assert!(result.is_err());
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn is_empty_contains_and_clear() {
+        let mut list = linkedlist![1, 2, 3];
+        assert!(!list.is_empty());
+        assert!(list.contains(&2));
+        assert!(!list.contains(&4));
+
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(list.count(), 0);
+        assert!(!list.contains(&2));
+        assert_eq!(list.first(), None);
+        assert_eq!(list.last(), None);
+
+        list.add_last(4);
+        assert_eq!(list, linkedlist![4]);
+    }
+
+    #[test]
+    fn append_moves_everything() {
+        let mut list = linkedlist![1, 2];
+        let mut other = linkedlist![3, 4, 5];
+        list.append(&mut other);
+        assert_eq!(list, linkedlist![1, 2, 3, 4, 5]);
+        assert_eq!(list.iter().rev().count(), 5);
+        assert_eq!(list.count(), 5);
+        assert!(other.is_empty());
+
+        let mut empty = LinkedList::new();
+        empty.append(&mut list);
+        assert_eq!(empty, linkedlist![1, 2, 3, 4, 5]);
+        empty.append(&mut list);
+        assert_eq!(empty.count(), 5);
+        assert_eq!(empty.last(), Some(&5));
+    }
+
+    #[test]
+    fn split_off_everywhere() {
+        for at in 0..=5 {
+            let mut list: LinkedList<_> = (0..5).collect();
+            let second = list.split_off(at);
+
+            assert_eq!(list.count(), at);
+            assert_eq!(second.count(), 5 - at);
+            assert_eq!(
+                list.iter().copied().collect::<Vec<_>>(),
+                (0..at).collect::<Vec<_>>()
+            );
+            assert_eq!(list.iter().rev().count(), at);
+            assert_eq!(
+                second.iter().copied().collect::<Vec<_>>(),
+                (at..5).collect::<Vec<_>>()
+            );
+            assert_eq!(second.iter().rev().count(), 5 - at);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_off_past_the_end_panics() {
+        linkedlist![1, 2].split_off(3);
+    }
+
+    #[test]
+    fn remove_and_insert() {
+        let mut list = linkedlist![0, 1, 2, 3, 4];
+        assert_eq!(list.remove(0), 0);
+        assert_eq!(list.remove(3), 4);
+        assert_eq!(list.remove(1), 2);
+        assert_eq!(list, linkedlist![1, 3]);
+        assert_eq!(list.count(), 2);
+
+        list.insert(1, 2);
+        list.insert(0, 0);
+        list.insert(4, 4);
+        list.insert(3, 9);
+        assert_eq!(list, linkedlist![0, 1, 2, 9, 3, 4]);
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            vec![4, 3, 9, 2, 1, 0]
+        );
+        assert_eq!(list.count(), 6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn remove_past_the_end_panics() {
+        linkedlist![1, 2].remove(2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_past_the_end_panics() {
+        linkedlist![1, 2].insert(3, 3);
+    }
+
+    #[test]
+    fn truncate_drops_the_tail() {
+        let mut list = linkedlist![1, 2, 3, 4];
+        list.truncate(10);
+        assert_eq!(list.count(), 4);
+        list.truncate(2);
+        assert_eq!(list, linkedlist![1, 2]);
+        assert_eq!(list.last(), Some(&2));
+        list.truncate(0);
+        assert!(list.is_empty());
+        assert_eq!(list.first(), None);
+    }
+
+    #[test]
+    fn reverse_relinks_in_place() {
+        let mut list = linkedlist![1, 2, 3, 4];
+        list.reverse();
+        assert_eq!(list, linkedlist![4, 3, 2, 1]);
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(list.first(), Some(&4));
+        assert_eq!(list.last(), Some(&1));
+
+        list.add_last(0);
+        list.add_first(5);
+        assert_eq!(list, linkedlist![5, 4, 3, 2, 1, 0]);
+
+        let mut empty: LinkedList<i32> = LinkedList::new();
+        empty.reverse();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn retain_and_retain_mut() {
+        let mut list: LinkedList<_> = (1..=10).collect();
+        list.retain(|x| x % 3 != 0);
+        assert_eq!(list, linkedlist![1, 2, 4, 5, 7, 8, 10]);
+        assert_eq!(list.count(), 7);
+
+        list.retain_mut(|x| {
+            *x *= 10;
+            *x > 40
+        });
+        assert_eq!(list, linkedlist![50, 70, 80, 100]);
+        assert_eq!(list.iter().rev().count(), 4);
+        assert_eq!(list.count(), 4);
+
+        list.retain(|_| false);
+        assert!(list.is_empty());
+        assert_eq!(list.last(), None);
+    }
+
+    #[test]
+    fn extract_if_removes_matching_elements() {
+        let mut list: LinkedList<_> = (1..=10).collect();
+        let evens: Vec<_> = list.extract_if(|x| *x % 2 == 0).collect();
+        assert_eq!(evens, vec![2, 4, 6, 8, 10]);
+        assert_eq!(list, linkedlist![1, 3, 5, 7, 9]);
+        assert_eq!(list.count(), 5);
+
+        // Stopping early leaves the unvisited elements alone.
+        {
+            let mut extract = list.extract_if(|x| *x > 2);
+            assert_eq!(extract.next(), Some(3));
+        }
+        assert_eq!(list, linkedlist![1, 5, 7, 9]);
+        assert_eq!(list.count(), 4);
+    }
 }
@@ -0,0 +1,229 @@
+//! A persistent (immutable) singly-linked list. Adding an element creates a new list that shares
+//! all of its nodes with the old one, so keeping old versions around is cheap, which makes it a
+//! good fit for things like undo stacks.
+//!
+//! `List` uses `Rc` for sharing. With the `sync` feature enabled, `sync::List` does the same with
+//! `Arc` so lists can be shared between threads.
+
+/// Defines `List<T>` (along with its node and iterator types) using the given reference counted
+/// pointer, which must have `new`, `clone` and `into_inner` like `Rc` and `Arc` do.
+macro_rules! persistent_list {
+    ($ptr:ident) => {
+        struct Node<T> {
+            value: T,
+            next: Option<$ptr<Node<T>>>,
+        }
+
+        pub struct List<T> {
+            head: Option<$ptr<Node<T>>>,
+        }
+
+        impl<T> List<T> {
+            pub fn new() -> List<T> {
+                List { head: None }
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.head.is_none()
+            }
+
+            /// Returns a new list with `value` in front of this list's elements, sharing them.
+            pub fn prepend(&self, value: T) -> List<T> {
+                List {
+                    head: Some($ptr::new(Node {
+                        value,
+                        next: self.head.clone(),
+                    })),
+                }
+            }
+
+            /// Returns the list without its first element, sharing the rest. The tail of an empty
+            /// list is an empty list.
+            pub fn tail(&self) -> List<T> {
+                List {
+                    head: self.head.as_ref().and_then(|node| node.next.clone()),
+                }
+            }
+
+            pub fn head(&self) -> Option<&T> {
+                self.head.as_ref().map(|node| &node.value)
+            }
+
+            pub fn iter(&self) -> Iter<'_, T> {
+                Iter {
+                    next: self.head.as_deref(),
+                }
+            }
+        }
+
+        impl<T> Clone for List<T> {
+            /// Cloning a list is O(1): the copy shares every node with the original.
+            fn clone(&self) -> Self {
+                List {
+                    head: self.head.clone(),
+                }
+            }
+        }
+
+        impl<T> Default for List<T> {
+            fn default() -> Self {
+                List::new()
+            }
+        }
+
+        impl<T: std::fmt::Debug> std::fmt::Debug for List<T> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_list().entries(self).finish()
+            }
+        }
+
+        impl<T> Drop for List<T> {
+            fn drop(&mut self) {
+                // Walk down the chain for as long as we hold the only reference to each node, so
+                // dropping a long list doesn't recurse once per node. As soon as we find a node
+                // that's shared with another list, the rest of the chain is still in use.
+                // `into_inner` (unlike `try_unwrap`) guarantees that when two lists sharing a
+                // node are dropped at the same time, exactly one of them carries on.
+                let mut head = self.head.take();
+                while let Some(node) = head {
+                    match $ptr::into_inner(node) {
+                        Some(mut node) => head = node.next.take(),
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        pub struct Iter<'a, T> {
+            next: Option<&'a Node<T>>,
+        }
+
+        impl<'a, T> Iterator for Iter<'a, T> {
+            type Item = &'a T;
+
+            fn next(&mut self) -> Option<&'a T> {
+                self.next.map(|node| {
+                    self.next = node.next.as_deref();
+                    &node.value
+                })
+            }
+        }
+
+        impl<'a, T> IntoIterator for &'a List<T> {
+            type Item = &'a T;
+            type IntoIter = Iter<'a, T>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter()
+            }
+        }
+    };
+}
+
+use std::rc::Rc;
+
+persistent_list!(Rc);
+
+#[cfg(feature = "sync")]
+pub mod sync {
+    //! The thread-safe flavour of the persistent list, sharing nodes through `Arc`.
+
+    use std::sync::Arc;
+
+    persistent_list!(Arc);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_list() {
+        let list: List<i32> = List::new();
+        assert!(list.is_empty());
+        assert_eq!(list.head(), None);
+        assert!(list.tail().is_empty());
+        assert_eq!(list.iter().next(), None);
+    }
+
+    #[test]
+    fn prepend_head_and_tail() {
+        let list = List::new().prepend(3).prepend(2).prepend(1);
+        assert_eq!(list.head(), Some(&1));
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let list = list.tail();
+        assert_eq!(list.head(), Some(&2));
+        let list = list.tail();
+        assert_eq!(list.head(), Some(&3));
+        let list = list.tail();
+        assert_eq!(list.head(), None);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn versions_share_their_tails() {
+        let base = List::new().prepend(10).prepend(5);
+        let b = base.prepend(3);
+        let c = base.prepend(4);
+
+        assert_eq!(format!("{base:?}"), "[5, 10]");
+        assert_eq!(format!("{b:?}"), "[3, 5, 10]");
+        assert_eq!(format!("{c:?}"), "[4, 5, 10]");
+
+        // The tails aren't copies, they're the very same nodes.
+        assert!(std::ptr::eq(b.tail().head().unwrap(), base.head().unwrap()));
+        assert!(std::ptr::eq(c.tail().head().unwrap(), base.head().unwrap()));
+
+        // Dropping the base version leaves the others intact.
+        drop(base);
+        assert_eq!(b.iter().copied().collect::<Vec<_>>(), vec![3, 5, 10]);
+        assert_eq!(c.iter().copied().collect::<Vec<_>>(), vec![4, 5, 10]);
+    }
+
+    #[test]
+    fn dropping_one_version_frees_only_its_own_nodes() {
+        let tracker = Rc::new(());
+        let shared = List::new().prepend(Rc::clone(&tracker));
+        let longer = shared
+            .prepend(Rc::clone(&tracker))
+            .prepend(Rc::clone(&tracker));
+        assert_eq!(Rc::strong_count(&tracker), 4);
+
+        drop(longer);
+        assert_eq!(Rc::strong_count(&tracker), 2);
+        drop(shared);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn dropping_long_shared_chains_does_not_overflow() {
+        let mut list = List::new();
+        for i in 0..1_000_000 {
+            list = list.prepend(i);
+        }
+
+        let shared_tail = list.tail().tail();
+        drop(list);
+        assert_eq!(shared_tail.head(), Some(&999_997));
+        drop(shared_tail);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn sync_list_is_shared_between_threads() {
+        use std::thread;
+
+        let base = sync::List::new().prepend(2).prepend(1);
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let base = base.clone();
+                thread::spawn(move || base.prepend(i * 10).iter().sum::<i32>())
+            })
+            .collect();
+
+        let sums: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(sums, vec![3, 13, 23, 33]);
+        assert_eq!(base.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
@@ -0,0 +1,85 @@
+//! `serde` support, behind the `serde` feature. A `LinkedList` is serialized as a sequence of its
+//! elements, in order.
+
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::LinkedList;
+
+impl<T: Serialize> Serialize for LinkedList<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.count()))?;
+        for value in self {
+            seq.serialize_element(value)?;
+        }
+
+        seq.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for LinkedList<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(LinkedListVisitor(PhantomData))
+    }
+}
+
+struct LinkedListVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for LinkedListVisitor<T> {
+    type Value = LinkedList<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        // Elements go straight into the list as they're deserialized.
+        let mut list = LinkedList::new();
+        while let Some(value) = seq.next_element()? {
+            list.add_last(value);
+        }
+
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{linkedlist, LinkedList};
+
+    #[test]
+    fn serializes_as_a_sequence() {
+        let list = linkedlist![3, 1, 2];
+        assert_eq!(serde_json::to_string(&list).unwrap(), "[3,1,2]");
+
+        let empty: LinkedList<i32> = LinkedList::new();
+        assert_eq!(serde_json::to_string(&empty).unwrap(), "[]");
+    }
+
+    #[test]
+    fn deserializes_in_order() {
+        let list: LinkedList<String> = serde_json::from_str(r#"["b", "a", "c"]"#).unwrap();
+        assert_eq!(
+            list,
+            linkedlist!["b".to_string(), "a".to_string(), "c".to_string()]
+        );
+        assert_eq!(list.count(), 3);
+
+        let nested: LinkedList<LinkedList<u8>> = serde_json::from_str("[[1, 2], [], [3]]").unwrap();
+        assert_eq!(
+            nested,
+            linkedlist![linkedlist![1, 2], linkedlist![], linkedlist![3]]
+        );
+    }
+
+    #[test]
+    fn rejects_non_sequences() {
+        assert!(serde_json::from_str::<LinkedList<i32>>(r#"{"a": 1}"#).is_err());
+        assert!(serde_json::from_str::<LinkedList<i32>>(r#"[1, "two"]"#).is_err());
+    }
+}
@@ -0,0 +1,458 @@
+use std::{cmp::Ordering, ptr::NonNull};
+
+use crate::{LinkedList, LinkedListNode};
+
+type Link<T> = Option<NonNull<LinkedListNode<T>>>;
+
+/// One bin per bit of `usize`: bin `i` holds either nothing or a sorted run of `2^i` nodes, so
+/// this is enough for any list that fits in memory.
+const BIN_COUNT: usize = usize::BITS as usize;
+
+impl<T> LinkedList<T> {
+    /// Sorts the list in place. The sort is stable, O(n log n), and works by relinking the existing
+    /// nodes, so it doesn't allocate and no element is ever moved in memory.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(T::cmp);
+    }
+
+    /// Like `sort`, comparing elements with `compare`.
+    ///
+    /// If `compare` panics the list keeps all of its elements, in an unspecified order.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut sort = MergeSort::new(self);
+        sort.sort(&mut |a, b| compare(a, b) == Ordering::Less);
+    }
+
+    /// Like `sort`, comparing the keys `f` extracts from the elements.
+    pub fn sort_by_key<K, F>(&mut self, mut f: F)
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Merges the elements of `other`, which must already be sorted, into this list, which must
+    /// also be sorted. Elements of this list go before equal elements of `other`. Leaves `other`
+    /// empty.
+    pub fn merge(&mut self, other: &mut LinkedList<T>)
+    where
+        T: Ord,
+    {
+        self.merge_by(other, T::cmp);
+    }
+
+    /// Like `merge`, comparing elements with `compare`.
+    pub fn merge_by<F>(&mut self, other: &mut LinkedList<T>, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let Some((other_first, _, other_count)) = other.detach_all_nodes() else {
+            return;
+        };
+
+        self.count += other_count;
+        let mut sort = MergeSort::new(self);
+        sort.left = sort.unsorted.take();
+        sort.right = Some(other_first);
+        sort.merge(&mut |a, b| compare(a, b) == Ordering::Less);
+        sort.carry = sort.merged.take();
+    }
+
+    /// Removes consecutive repeated elements, keeping the first of each run.
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b);
+    }
+
+    /// Removes consecutive elements that map to the same key, keeping the first of each run.
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        K: PartialEq,
+        F: FnMut(&mut T) -> K,
+    {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// Removes consecutive elements for which `same_bucket(element, previous)` returns `true`,
+    /// where `previous` is the last element that was kept.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let Some(mut kept) = self.first else {
+            return;
+        };
+
+        // Safety: `kept` and `current` are always distinct live nodes of this list, and a node is
+        // only freed after being unlinked.
+        unsafe {
+            let mut current = (*kept.as_ptr()).next;
+            while let Some(node) = current {
+                current = (*node.as_ptr()).next;
+                if same_bucket(&mut (*node.as_ptr()).value, &mut (*kept.as_ptr()).value) {
+                    self.unlink_node(node);
+                    drop(Box::from_raw(node.as_ptr()));
+                } else {
+                    kept = node;
+                }
+            }
+        }
+    }
+}
+
+/// State for a bottom-up merge sort over a list's nodes.
+///
+/// While sorting, the nodes are only linked through `next` and are spread across several chains
+/// (the unsorted remainder, the bins, and the two halves of the merge in progress). Keeping all of
+/// them here means that, whether the sort finishes or `is_less` panics halfway through, `Drop` can
+/// stitch every chain back together and fix up the `prev` pointers, so no node is ever lost.
+struct MergeSort<'a, T> {
+    list: &'a mut LinkedList<T>,
+    unsorted: Link<T>,
+    bins: [Link<T>; BIN_COUNT],
+    carry: Link<T>,
+    left: Link<T>,
+    right: Link<T>,
+    merged: Link<T>,
+    merged_last: Link<T>,
+}
+
+impl<'a, T> MergeSort<'a, T> {
+    fn new(list: &'a mut LinkedList<T>) -> MergeSort<'a, T> {
+        MergeSort {
+            unsorted: list.first,
+            list,
+            bins: [None; BIN_COUNT],
+            carry: None,
+            left: None,
+            right: None,
+            merged: None,
+            merged_last: None,
+        }
+    }
+
+    fn sort(&mut self, is_less: &mut dyn FnMut(&T, &T) -> bool) {
+        // Safety: every pointer we follow belongs to a node of `list`, and each node is in exactly
+        // one of our chains at any given time.
+        while let Some(node) = self.unsorted {
+            unsafe { self.unsorted = (*node.as_ptr()).next.take() };
+            self.carry = Some(node);
+
+            // Add the node like incrementing a binary counter: a full bin gets merged with the
+            // carry and emptied, until we find an empty bin to put the carry in. A bin always
+            // holds elements that came before the carry's, so it goes on the left to keep the sort
+            // stable.
+            let mut bin = 0;
+            while let Some(run) = self.bins[bin].take() {
+                self.left = Some(run);
+                self.right = self.carry.take();
+                self.merge(is_less);
+                self.carry = self.merged.take();
+                bin += 1;
+            }
+
+            self.bins[bin] = self.carry.take();
+        }
+
+        // Higher bins hold earlier elements, so going upwards each bin goes on the left.
+        for bin in 0..BIN_COUNT {
+            if let Some(run) = self.bins[bin].take() {
+                self.left = Some(run);
+                self.right = self.carry.take();
+                self.merge(is_less);
+                self.carry = self.merged.take();
+            }
+        }
+    }
+
+    /// Merges the `left` and `right` chains into `merged`, taking from `left` on ties.
+    fn merge(&mut self, is_less: &mut dyn FnMut(&T, &T) -> bool) {
+        self.merged_last = None;
+
+        loop {
+            let node = match (self.left, self.right) {
+                (Some(left), Some(right)) => unsafe {
+                    if is_less(&(*right.as_ptr()).value, &(*left.as_ptr()).value) {
+                        self.right = (*right.as_ptr()).next;
+                        right
+                    } else {
+                        self.left = (*left.as_ptr()).next;
+                        left
+                    }
+                },
+                (Some(rest), None) | (None, Some(rest)) => {
+                    self.left = None;
+                    self.right = None;
+                    self.push_merged(rest);
+                    return;
+                }
+                (None, None) => return,
+            };
+
+            unsafe { (*node.as_ptr()).next = None };
+            self.push_merged(node);
+        }
+    }
+
+    /// Links `node` (and whatever chain follows it) after the last merged node.
+    fn push_merged(&mut self, node: NonNull<LinkedListNode<T>>) {
+        match self.merged_last {
+            Some(last) => unsafe { (*last.as_ptr()).next = Some(node) },
+            None => self.merged = Some(node),
+        }
+
+        self.merged_last = Some(node);
+    }
+}
+
+impl<T> Drop for MergeSort<'_, T> {
+    fn drop(&mut self) {
+        // On success everything is in `carry`. If `is_less` panicked, the nodes may be anywhere,
+        // so we just chain all of them together in whatever order they're in.
+        let chains = [
+            self.carry,
+            self.merged,
+            self.left,
+            self.right,
+            self.unsorted,
+        ];
+        let mut first = None;
+        let mut last: Link<T> = None;
+        for chain in chains.into_iter().chain(self.bins) {
+            let mut current = chain;
+            while let Some(node) = current {
+                // Safety: each node is visited exactly once, and `last` is the node visited before.
+                unsafe {
+                    (*node.as_ptr()).prev = last;
+                    match last {
+                        Some(last) => (*last.as_ptr()).next = Some(node),
+                        None => first = Some(node),
+                    }
+                    current = (*node.as_ptr()).next;
+                }
+                last = Some(node);
+            }
+        }
+
+        if let Some(last) = last {
+            unsafe { (*last.as_ptr()).next = None };
+        }
+
+        self.list.first = first;
+        self.list.last = last;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use crate::{linkedlist, LinkedList};
+
+    /// A tiny xorshift generator, so the property tests are reproducible without extra crates.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: u64) -> u64 {
+            self.next() % bound
+        }
+    }
+
+    fn assert_links_ok<T: PartialEq + std::fmt::Debug>(list: &LinkedList<T>) {
+        let forwards: Vec<_> = list.iter().collect();
+        let mut backwards: Vec<_> = list.iter().rev().collect();
+        backwards.reverse();
+        assert_eq!(forwards, backwards);
+        assert_eq!(forwards.len(), list.count());
+        assert_eq!(list.first(), forwards.first().copied());
+        assert_eq!(list.last(), forwards.last().copied());
+    }
+
+    #[test]
+    fn sort_small_lists() {
+        let mut list: LinkedList<i32> = linkedlist![];
+        list.sort();
+        assert!(list.is_empty());
+
+        let mut list = linkedlist![1];
+        list.sort();
+        assert_eq!(list, linkedlist![1]);
+
+        let mut list = linkedlist![5, 3, 1, 4, 2];
+        list.sort();
+        assert_eq!(list, linkedlist![1, 2, 3, 4, 5]);
+        assert_links_ok(&list);
+
+        list.sort_by(|a, b| b.cmp(a));
+        assert_eq!(list, linkedlist![5, 4, 3, 2, 1]);
+        assert_links_ok(&list);
+    }
+
+    #[test]
+    fn sort_matches_vec_sort() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..300 {
+            let len = rng.below(300) as usize;
+            let range = rng.below(50) + 1;
+            let values: Vec<u64> = (0..len).map(|_| rng.below(range)).collect();
+
+            let mut expected = values.clone();
+            expected.sort();
+            let mut list: LinkedList<_> = values.into_iter().collect();
+            list.sort();
+
+            assert_eq!(list.iter().copied().collect::<Vec<_>>(), expected);
+            assert_links_ok(&list);
+        }
+    }
+
+    #[test]
+    fn sort_is_stable() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            let len = rng.below(300) as usize;
+            // Few distinct keys, so there are lots of ties. The second field remembers the
+            // original position.
+            let values: Vec<(u64, usize)> = (0..len).map(|i| (rng.below(8), i)).collect();
+
+            let mut expected = values.clone();
+            expected.sort_by_key(|(key, _)| *key);
+            let mut list: LinkedList<_> = values.into_iter().collect();
+            list.sort_by_key(|(key, _)| *key);
+
+            assert_eq!(list.iter().copied().collect::<Vec<_>>(), expected);
+            assert_links_ok(&list);
+        }
+    }
+
+    #[test]
+    fn sort_keeps_the_nodes() {
+        let mut list = linkedlist![3, 1, 2];
+        let mut addresses: Vec<*const i32> = list.iter().map(|x| x as *const i32).collect();
+        list.sort();
+
+        let mut sorted_addresses: Vec<*const i32> = list.iter().map(|x| x as *const i32).collect();
+        assert_ne!(addresses, sorted_addresses);
+        sorted_addresses.sort();
+        addresses.sort();
+        assert_eq!(addresses, sorted_addresses);
+    }
+
+    #[test]
+    fn panicking_compare_keeps_every_element() {
+        let mut list: LinkedList<_> = (0..100).rev().map(|x| x.to_string()).collect();
+        let mut comparisons = 0;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            list.sort_by(|a, b| {
+                comparisons += 1;
+                if comparisons == 250 {
+                    panic!("compare panicked");
+                }
+                a.cmp(b)
+            })
+        }));
+
+        assert!(result.is_err());
+        assert_links_ok(&list);
+        let mut values: Vec<_> = list.iter().map(|x| x.parse::<i32>().unwrap()).collect();
+        values.sort();
+        assert_eq!(values, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn merge_sorted_lists() {
+        let mut list = linkedlist![(1, 'a'), (3, 'a'), (5, 'a')];
+        let mut other = linkedlist![(0, 'b'), (3, 'b'), (6, 'b')];
+        list.merge_by(&mut other, |a, b| a.0.cmp(&b.0));
+
+        let expected = linkedlist![(0, 'b'), (1, 'a'), (3, 'a'), (3, 'b'), (5, 'a'), (6, 'b')];
+        assert_eq!(list, expected);
+        assert_links_ok(&list);
+        assert!(other.is_empty());
+
+        let mut empty = LinkedList::new();
+        empty.merge(&mut linkedlist![1, 2]);
+        assert_eq!(empty, linkedlist![1, 2]);
+        empty.merge(&mut LinkedList::new());
+        assert_eq!(empty, linkedlist![1, 2]);
+        assert_links_ok(&empty);
+    }
+
+    #[test]
+    fn merge_matches_vec_sort() {
+        let mut rng = Rng(0x1234_5678_9abc_def1);
+        for _ in 0..200 {
+            let mut left: Vec<u64> = (0..rng.below(100)).map(|_| rng.below(30)).collect();
+            let mut right: Vec<u64> = (0..rng.below(100)).map(|_| rng.below(30)).collect();
+            left.sort();
+            right.sort();
+
+            let mut list: LinkedList<_> = left.iter().copied().collect();
+            list.merge(&mut right.iter().copied().collect());
+            let mut expected = [left, right].concat();
+            expected.sort();
+
+            assert_eq!(list.iter().copied().collect::<Vec<_>>(), expected);
+            assert_links_ok(&list);
+        }
+    }
+
+    #[test]
+    fn dedup_removes_consecutive_repeats() {
+        let mut list = linkedlist![1, 1, 2, 3, 3, 3, 1, 4, 4];
+        list.dedup();
+        assert_eq!(list, linkedlist![1, 2, 3, 1, 4]);
+        assert_links_ok(&list);
+
+        let mut list = linkedlist![10, 11, 20, 21, 22, 30];
+        list.dedup_by_key(|x| *x / 10);
+        assert_eq!(list, linkedlist![10, 20, 30]);
+        assert_links_ok(&list);
+
+        let mut list = linkedlist![1, 2, 4, 7, 8];
+        list.dedup_by(|current, kept| *current - *kept < 3);
+        assert_eq!(list, linkedlist![1, 4, 7]);
+        assert_links_ok(&list);
+    }
+
+    #[test]
+    fn sort_then_dedup_matches_vec() {
+        let mut rng = Rng(0xdead_beef_cafe_f00d);
+        for _ in 0..100 {
+            let values: Vec<u64> = (0..rng.below(200)).map(|_| rng.below(20)).collect();
+            let mut expected = values.clone();
+            expected.sort();
+            expected.dedup();
+
+            let mut list: LinkedList<_> = values.into_iter().collect();
+            list.sort();
+            list.dedup();
+            assert_eq!(list.iter().copied().collect::<Vec<_>>(), expected);
+            assert_links_ok(&list);
+        }
+    }
+
+    #[test]
+    fn sort_reverse_sorted_input() {
+        let mut list: LinkedList<_> = (0..1000).rev().collect();
+        list.sort();
+        assert_eq!(list, (0..1000).collect());
+        assert_links_ok(&list);
+    }
+}
@@ -0,0 +1,77 @@
+//! Compares searching big inputs with rare matches against the naive approach of checking every
+//! line, which is what `search_with` used to do.
+//!
+//! Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use minigrep::{
+    search_reader, search_with, LiteralMatcher, LiteralSetMatcher, Matcher, RegexMatcher,
+};
+
+/// About 16 MiB of log-like lines, with a line containing the needle every 50000 lines.
+fn haystack() -> String {
+    let mut haystack = String::new();
+    for i in 0..200_000 {
+        if i % 50_000 == 25_000 {
+            haystack.push_str("2024-01-01 12:00:00 ERROR the needle was found here\n");
+        } else {
+            haystack.push_str(&format!(
+                "2024-01-01 12:00:00 INFO request {i} handled in {} ms by worker {}\n",
+                i % 997,
+                i % 16
+            ));
+        }
+    }
+
+    haystack
+}
+
+fn naive(matcher: &dyn Matcher, contents: &str) -> usize {
+    contents
+        .lines()
+        .filter(|line| matcher.is_match(line.as_bytes()))
+        .count()
+}
+
+fn bench_matcher(c: &mut Criterion, name: &str, matcher: &dyn Matcher) {
+    let haystack = haystack();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(haystack.len() as u64));
+
+    group.bench_function("line by line", |b| {
+        b.iter(|| naive(matcher, black_box(&haystack)))
+    });
+    group.bench_function("search_with", |b| {
+        b.iter(|| search_with(matcher, black_box(&haystack)).len())
+    });
+    group.bench_function("search_reader", |b| {
+        b.iter(|| search_reader(matcher, black_box(haystack.as_bytes())).count())
+    });
+
+    group.finish();
+}
+
+fn literal(c: &mut Criterion) {
+    bench_matcher(c, "literal", &LiteralMatcher::new("needle"));
+}
+
+fn regex(c: &mut Criterion) {
+    bench_matcher(
+        c,
+        "regex",
+        &RegexMatcher::new(r"ERROR .*needle", false).unwrap(),
+    );
+}
+
+fn literal_set(c: &mut Criterion) {
+    // Hundreds of request IDs, none of which are in the haystack.
+    let needles: Vec<_> = (0..300).map(|i| format!("request {}x", i * 7)).collect();
+    bench_matcher(
+        c,
+        "literal set",
+        &LiteralSetMatcher::new(&needles, false).unwrap(),
+    );
+}
+
+criterion_group!(benches, literal, regex, literal_set);
+criterion_main!(benches);
@@ -0,0 +1,941 @@
+use std::{env, error::Error, ffi::OsString, fmt, fs, path::PathBuf};
+
+use crate::matcher::{
+    CaseInsensitiveMatcher, FuzzyMatcher, LiteralMatcher, LiteralSetMatcher, Matcher, RegexMatcher,
+    WholeLineMatcher, WholeWordMatcher,
+};
+
+const HELP: &str = "\
+Usage: minigrep [OPTIONS] <query> [<path>...]
+       minigrep [OPTIONS] -e <PATTERN>... [<path>...]
+
+Prints every line of each <path> that contains <query>. A <path> of '-' means standard input, which
+is also what's searched if there are no paths (unless searching recursively).
+
+Options:
+  -e, --regexp <PATTERN>    Search for PATTERN instead of <query> (can be repeated, to print the
+                            lines that match any of them)
+  -f, --file <FILE>         Search for every pattern in FILE (one per line) instead of <query>
+                            (can be repeated, and combined with -e)
+  -E, --regex               Treat <query> (or the patterns) as a regular expression
+  -i, --ignore-case         Ignore case distinctions (overrides IGNORE_CASE)
+      --no-ignore-case      Match case exactly, even if IGNORE_CASE is set
+  -S, --smart-case          Ignore case unless a pattern has uppercase letters (-i and
+                            --no-ignore-case take precedence)
+  -v, --invert-match        Print the lines that don't match instead
+  -w, --word-regexp         Only match whole words
+  -x, --line-regexp         Only match whole lines
+      --fuzzy <N>           Also match text up to N edits (inserted, deleted or substituted
+                            characters) away from <query>, printing how many each line needed
+                            after a '~'
+  -n, --line-number         Prefix each line with its line number
+  -c, --count               Only print how many lines matched
+  -l, --files-with-matches  Only print the name of the file if it has any match
+  -r, --recursive           Search every file under each <path> (or the current directory),
+                            skipping binary files, hidden files and anything ignored by a
+                            .gitignore or .ignore file
+      --include <GLOB>      With -r, only search files matching GLOB (can be repeated)
+      --exclude <GLOB>      With -r, skip files matching GLOB (can be repeated)
+  -j, --threads <N>         Search N files at the same time (the output of each file is still
+                            printed all together)
+      --sort <KEY>          Sort the output: by 'path' (which is slower with -r, as directories
+                            have to be sorted), by 'score' (with --fuzzy, closest lines first,
+                            which are only printed once the search is done), or 'none' (the
+                            default)
+      --binary-files <TYPE> What to do with binary files: 'binary' (only print whether they
+                            match), 'text' (search them like any other file) or 'without-match'
+                            (skip them). Without this, files found with -r are skipped and
+                            the rest are treated as 'binary'
+  -z, --search-zip          Also search the contents of gzip, zstd and bzip2 compressed files
+  -A, --after-context <N>   Also print the N lines after each selected line
+  -B, --before-context <N>  Also print the N lines before each selected line
+  -C, --context <N>         Same as -A N -B N
+      --color <WHEN>        Highlight matches with colors: 'auto' (the default, only when
+                            writing to a terminal), 'always' or 'never'
+  -o, --only-matching       Only print the matched parts of each line, one per line
+      --replace <TEXT>      Print the selected lines with every match replaced by TEXT. In regex
+                            mode, $1 or ${name} stand for what a group matched ($$ for a '$')
+      --in-place            With --replace, rewrite the files with the replacements made instead
+                            of printing anything
+      --dry-run             With --in-place, print a diff of the changes instead of making them
+      --json                Print one JSON object per line, with its path, line number, byte
+                            offset and matches (-c and -l still print plain text)
+  -h, --help                Print this help and exit
+  -V, --version             Print the version and exit
+      --                    Treat every argument after this as positional
+
+Setting the IGNORE_CASE environment variable acts like -i, unless a flag says otherwise. Case is
+compared using Unicode case folding, so for example 'STRASSE' matches 'straße' (though in regex
+mode, only characters with a single-character folding are matched this way).
+
+Files don't have to be valid UTF-8: invalid bytes never match, and are printed as '�'. A file is
+binary if it has a NUL byte near the start.";
+
+const TRY_HELP: &str = "Try 'minigrep --help' for more information.";
+
+#[derive(Debug, Default)]
+pub struct Config {
+    /// What to search for: lines matching any of these are selected. Usually it's just the
+    /// `<query>`, but `-e` and `-f` can give any number of them.
+    pub patterns: Vec<String>,
+    /// The files (or directories, with `-r`) to search. `-` stands for stdin.
+    pub paths: Vec<PathBuf>,
+    pub ignore_case: bool,
+    pub smart_case: bool,
+    pub regex: bool,
+    pub invert_match: bool,
+    pub whole_word: bool,
+    pub whole_line: bool,
+    pub line_number: bool,
+    pub count: bool,
+    pub files_with_matches: bool,
+    pub recursive: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub before_context: usize,
+    pub after_context: usize,
+    pub color: ColorChoice,
+    pub only_matching: bool,
+    pub json: bool,
+    /// With `--fuzzy`, how many edits away from the query a match can be.
+    pub fuzzy: Option<usize>,
+    /// What to replace every match with, if anything.
+    pub replace: Option<String>,
+    /// Whether to write the replacements back to the files rather than printing them.
+    pub in_place: bool,
+    /// With `in_place`, whether to only print what would change instead.
+    pub dry_run: bool,
+    /// How many inputs to search at the same time. Both 0 and 1 mean one after the other.
+    pub threads: usize,
+    pub sort_by: Option<SortBy>,
+    /// What to do with binary files. `None` skips the ones found while walking directories, and
+    /// treats the others as `BinaryFiles::Binary`.
+    pub binary_files: Option<BinaryFiles>,
+    /// Whether to search what compressed files hold rather than the compressed data.
+    pub search_zip: bool,
+}
+
+/// How to search files that look binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFiles {
+    /// Only print whether the file matches, instead of its lines.
+    Binary,
+    /// Search the file as if it were text.
+    Text,
+    /// Skip the file, as if it didn't match.
+    WithoutMatch,
+}
+
+/// What to sort the output by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// Files are searched (and printed) sorted by their path. Paths given on the command line are
+    /// still searched in the order given.
+    Path,
+    /// Selected lines are printed from the closest `--fuzzy` match to the furthest. Lines that are
+    /// just as close are printed in the order they were found.
+    Score,
+}
+
+/// When to highlight the output with colors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Why `Config::build` didn't return a `Config`. `Help` and `Version` aren't really errors, the
+/// user just asked for something other than a search.
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Help,
+    Version,
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Help => write!(f, "{HELP}"),
+            ConfigError::Version => write!(f, "minigrep {}", env!("CARGO_PKG_VERSION")),
+            ConfigError::Invalid(message) => write!(f, "minigrep: {message}\n{TRY_HELP}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Builds a config from the command line arguments, the first one being the program name.
+    ///
+    /// Arguments are taken as `OsString`s, since paths don't have to be valid Unicode. Everything
+    /// else (options and the query) does.
+    pub fn build(args: impl Iterator<Item = OsString>) -> Result<Config, ConfigError> {
+        Config::build_with_env(args, env::var("IGNORE_CASE").is_ok())
+    }
+
+    /// Does the actual work for `build`, taking whether `IGNORE_CASE` is set as a parameter so it
+    /// can be tested without touching the environment.
+    fn build_with_env(
+        mut args: impl Iterator<Item = OsString>,
+        ignore_case_env: bool,
+    ) -> Result<Config, ConfigError> {
+        // If we wanted to assign args[1] to the `query` variable and args[2] to the `file_path variable`,
+        // we'd have to use `.clone()`, as we can't take ownership of the String away from the args array:
+        // (`args` used to be a `&[String]`)
+        // let query = args[1].clone();
+        // let file_path = args[2].clone();
+
+        // However, we can instead use an iterator that returns ownership of these strings!
+        args.next();
+
+        let mut config = Config::default();
+        let mut ignore_case = None;
+        let mut positional = Vec::new();
+        // Whether any patterns were given with `-e` or `-f`, in which case there's no `<query>`.
+        let mut patterns_given = false;
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                positional.extend(args.by_ref());
+                break;
+            }
+
+            // Only positional arguments can be invalid Unicode, anything starting with `-` has to
+            // be an option (and `-` alone is a path).
+            let arg = match arg.into_string() {
+                Ok(arg) => arg,
+                Err(arg) if arg.as_encoded_bytes().starts_with(b"-") => {
+                    return Err(invalid(format!(
+                        "invalid Unicode in option '{}'",
+                        arg.to_string_lossy()
+                    )));
+                }
+                Err(arg) => {
+                    positional.push(arg);
+                    continue;
+                }
+            };
+
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, inline_value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+
+                if takes_value(name) {
+                    let value = match inline_value {
+                        Some(value) => value,
+                        None => next_value(&mut args, &format!("--{name}"))?,
+                    };
+                    patterns_given |= is_pattern_option(name);
+                    config.apply_option(name, value)?;
+                } else if inline_value.is_some() {
+                    return Err(invalid(format!("option '--{name}' doesn't take a value")));
+                } else {
+                    config.apply_flag(name, &mut ignore_case)?;
+                }
+            } else if let Some(shorts) = arg.strip_prefix('-').filter(|s| !s.is_empty()) {
+                // Short flags can be bundled together, like `-in`. An option that takes a value
+                // uses up the rest of the bundle, or the next argument if it's the last one.
+                for (index, short) in shorts.char_indices() {
+                    let long = long_name(short)
+                        .ok_or_else(|| invalid(format!("unknown option '-{short}'")))?;
+
+                    if takes_value(long) {
+                        let rest = &shorts[index + short.len_utf8()..];
+                        let value = match rest {
+                            "" => next_value(&mut args, &format!("-{short}"))?,
+                            rest => rest.to_string(),
+                        };
+                        patterns_given |= is_pattern_option(long);
+                        config.apply_option(long, value)?;
+                        break;
+                    }
+
+                    config.apply_flag(long, &mut ignore_case)?;
+                }
+            } else {
+                positional.push(arg.into());
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        if !patterns_given {
+            let query = positional
+                .next()
+                .ok_or_else(|| invalid("missing <query> argument".to_string()))?
+                .into_string()
+                .map_err(|query| {
+                    invalid(format!(
+                        "invalid Unicode in <query> '{}'",
+                        query.to_string_lossy()
+                    ))
+                })?;
+            config.patterns.push(query);
+        }
+
+        config.paths = positional.map(PathBuf::from).collect();
+        if config.paths.is_empty() {
+            let default = if config.recursive { "." } else { "-" };
+            config.paths.push(default.into());
+        }
+
+        config.ignore_case = ignore_case.unwrap_or_else(|| {
+            if config.smart_case {
+                !config
+                    .patterns
+                    .iter()
+                    .any(|pattern| pattern.chars().any(char::is_uppercase))
+            } else {
+                ignore_case_env
+            }
+        });
+
+        if config.in_place && config.replace.is_none() {
+            return Err(invalid("--in-place needs --replace".to_string()));
+        } else if config.in_place && config.invert_match {
+            return Err(invalid("--in-place can't be used with -v".to_string()));
+        } else if config.in_place && config.search_zip {
+            return Err(invalid("--in-place can't be used with -z".to_string()));
+        } else if config.dry_run && !config.in_place {
+            return Err(invalid("--dry-run needs --in-place".to_string()));
+        }
+
+        if config.fuzzy.is_some() {
+            if config.regex {
+                return Err(invalid("--fuzzy can't be used with -E".to_string()));
+            } else if config.whole_word || config.whole_line {
+                return Err(invalid("--fuzzy can't be used with -w or -x".to_string()));
+            } else if config.patterns.len() != 1 {
+                return Err(invalid("--fuzzy needs a single pattern".to_string()));
+            }
+        }
+
+        if config.sort_by == Some(SortBy::Score) {
+            if config.fuzzy.is_none() {
+                return Err(invalid("--sort=score needs --fuzzy".to_string()));
+            } else if config.invert_match {
+                return Err(invalid("--sort=score can't be used with -v".to_string()));
+            } else if config.before_context > 0 || config.after_context > 0 {
+                return Err(invalid(
+                    "--sort=score can't be used with context".to_string(),
+                ));
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn apply_flag(
+        &mut self,
+        name: &str,
+        ignore_case: &mut Option<bool>,
+    ) -> Result<(), ConfigError> {
+        match name {
+            "regex" => self.regex = true,
+            "ignore-case" => *ignore_case = Some(true),
+            "no-ignore-case" => *ignore_case = Some(false),
+            "smart-case" => self.smart_case = true,
+            "invert-match" => self.invert_match = true,
+            "word-regexp" => self.whole_word = true,
+            "line-regexp" => self.whole_line = true,
+            "line-number" => self.line_number = true,
+            "count" => self.count = true,
+            "files-with-matches" => self.files_with_matches = true,
+            "only-matching" => self.only_matching = true,
+            "json" => self.json = true,
+            "in-place" => self.in_place = true,
+            "dry-run" => self.dry_run = true,
+            "recursive" => self.recursive = true,
+            "search-zip" => self.search_zip = true,
+            "help" => return Err(ConfigError::Help),
+            "version" => return Err(ConfigError::Version),
+            _ => return Err(invalid(format!("unknown option '--{name}'"))),
+        }
+
+        Ok(())
+    }
+
+    fn apply_option(&mut self, name: &str, value: String) -> Result<(), ConfigError> {
+        match name {
+            "regexp" => self.patterns.push(value),
+            "file" => {
+                let patterns =
+                    fs::read_to_string(&value).map_err(|e| invalid(format!("{value}: {e}")))?;
+                self.patterns.extend(patterns.lines().map(String::from));
+            }
+            "include" => self.include.push(value),
+            "replace" => self.replace = Some(value),
+            "exclude" => self.exclude.push(value),
+            "color" => {
+                self.color = match value.as_str() {
+                    "auto" => ColorChoice::Auto,
+                    "always" => ColorChoice::Always,
+                    "never" => ColorChoice::Never,
+                    _ => return Err(invalid(format!("invalid value '{value}' for '--color'"))),
+                }
+            }
+            "threads" => {
+                self.threads = match value.parse() {
+                    Ok(0) | Err(_) => {
+                        return Err(invalid(format!("invalid number of threads '{value}'")))
+                    }
+                    Ok(threads) => threads,
+                }
+            }
+            "sort" => {
+                self.sort_by = match value.as_str() {
+                    "none" => None,
+                    "path" => Some(SortBy::Path),
+                    "score" => Some(SortBy::Score),
+                    _ => return Err(invalid(format!("invalid value '{value}' for '--sort'"))),
+                }
+            }
+            "binary-files" => {
+                self.binary_files = match value.as_str() {
+                    "binary" => Some(BinaryFiles::Binary),
+                    "text" => Some(BinaryFiles::Text),
+                    "without-match" => Some(BinaryFiles::WithoutMatch),
+                    _ => {
+                        return Err(invalid(format!(
+                            "invalid value '{value}' for '--binary-files'"
+                        )))
+                    }
+                }
+            }
+            "fuzzy" => {
+                self.fuzzy = match value.parse() {
+                    Ok(distance) => Some(distance),
+                    Err(_) => return Err(invalid(format!("invalid edit distance '{value}'"))),
+                }
+            }
+            "after-context" => self.after_context = parse_context(&value)?,
+            "before-context" => self.before_context = parse_context(&value)?,
+            "context" => {
+                self.after_context = parse_context(&value)?;
+                self.before_context = self.after_context;
+            }
+            _ => return Err(invalid(format!("unknown option '--{name}'"))),
+        }
+
+        Ok(())
+    }
+
+    /// Builds the `Matcher` for the search mode this config asks for.
+    ///
+    /// All the patterns are searched for at once, in a single pass over the text: regexes are
+    /// joined into one big alternation, and several literal strings are searched for with a
+    /// `LiteralSetMatcher`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if in regex mode and a pattern isn't a valid regular expression, or if there are too
+    /// many patterns to search for.
+    pub fn matcher(&self) -> Result<Box<dyn Matcher>, Box<dyn Error>> {
+        if let (Some(max_distance), [pattern]) = (self.fuzzy, self.patterns.as_slice()) {
+            return Ok(Box::new(FuzzyMatcher::new(
+                pattern,
+                max_distance,
+                self.ignore_case,
+            )));
+        }
+
+        let mut matcher: Box<dyn Matcher> = match self.patterns.as_slice() {
+            // With no patterns at all (like from an empty `-f` file) nothing matches, but an
+            // empty alternation would match everything.
+            [] => Box::new(LiteralSetMatcher::new(&[], false)?),
+            patterns if self.regex => {
+                let pattern = match patterns {
+                    [pattern] => pattern.clone(),
+                    // Note: The groups keep their numbers, counting from the first pattern.
+                    patterns => patterns
+                        .iter()
+                        .map(|pattern| format!("(?:{pattern})"))
+                        .collect::<Vec<_>>()
+                        .join("|"),
+                };
+
+                // Anchoring the whole pattern (rather than checking the match afterwards) makes
+                // sure alternations like `ab|abc` can still match a whole line or word with their
+                // longer branch. Like in grep, `-w` does nothing when `-x` is given too.
+                let pattern = if self.whole_line {
+                    format!("^(?:{pattern})$")
+                } else if self.whole_word {
+                    format!(r"\b{{start-half}}(?:{pattern})\b{{end-half}}")
+                } else {
+                    pattern
+                };
+
+                return Ok(Box::new(RegexMatcher::new(&pattern, self.ignore_case)?));
+            }
+            [pattern] if self.ignore_case => Box::new(CaseInsensitiveMatcher::new(pattern)),
+            [pattern] => Box::new(LiteralMatcher::new(pattern)),
+            patterns => Box::new(LiteralSetMatcher::new(patterns, self.ignore_case)?),
+        };
+
+        if self.whole_line {
+            matcher = Box::new(WholeLineMatcher::new(matcher));
+        }
+
+        if self.whole_word {
+            matcher = Box::new(WholeWordMatcher::new(matcher));
+        }
+
+        Ok(matcher)
+    }
+}
+
+fn invalid(message: String) -> ConfigError {
+    ConfigError::Invalid(message)
+}
+
+/// Takes the value of `option` from the next argument.
+fn next_value(
+    args: &mut impl Iterator<Item = OsString>,
+    option: &str,
+) -> Result<String, ConfigError> {
+    let value = args
+        .next()
+        .ok_or_else(|| invalid(format!("option '{option}' requires a value")))?;
+
+    value.into_string().map_err(|value| {
+        invalid(format!(
+            "invalid Unicode in value '{}' for '{option}'",
+            value.to_string_lossy()
+        ))
+    })
+}
+
+/// Whether the option `name` gives patterns to search for.
+fn is_pattern_option(name: &str) -> bool {
+    matches!(name, "regexp" | "file")
+}
+
+fn takes_value(name: &str) -> bool {
+    matches!(
+        name,
+        "regexp"
+            | "file"
+            | "include"
+            | "exclude"
+            | "replace"
+            | "color"
+            | "threads"
+            | "sort"
+            | "fuzzy"
+            | "binary-files"
+            | "after-context"
+            | "before-context"
+            | "context"
+    )
+}
+
+fn parse_context(value: &str) -> Result<usize, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("invalid context length '{value}'")))
+}
+
+fn long_name(short: char) -> Option<&'static str> {
+    let long = match short {
+        'e' => "regexp",
+        'f' => "file",
+        'E' => "regex",
+        'i' => "ignore-case",
+        'S' => "smart-case",
+        'v' => "invert-match",
+        'w' => "word-regexp",
+        'x' => "line-regexp",
+        'n' => "line-number",
+        'c' => "count",
+        'l' => "files-with-matches",
+        'o' => "only-matching",
+        'r' => "recursive",
+        'z' => "search-zip",
+        'j' => "threads",
+        'A' => "after-context",
+        'B' => "before-context",
+        'C' => "context",
+        'h' => "help",
+        'V' => "version",
+        _ => return None,
+    };
+
+    Some(long)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(args: &[&str], ignore_case_env: bool) -> Result<Config, ConfigError> {
+        let args = ["minigrep"].iter().chain(args).map(OsString::from);
+        Config::build_with_env(args, ignore_case_env)
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn positional_only() {
+        let config = build(&["duct", "poem.txt"], false).unwrap();
+        assert_eq!(config.patterns, vec!["duct"]);
+        assert_eq!(config.paths, paths(&["poem.txt"]));
+        assert!(!config.ignore_case);
+        assert!(!config.regex);
+    }
+
+    #[test]
+    fn short_and_long_flags() {
+        let config = build(
+            &["-v", "--line-number", "duct", "-c", "poem.txt", "-l"],
+            false,
+        )
+        .unwrap();
+        assert!(config.invert_match);
+        assert!(config.line_number);
+        assert!(config.count);
+        assert!(config.files_with_matches);
+        assert!(!config.whole_word);
+
+        let config = build(&["-wxE", "duct", "poem.txt"], false).unwrap();
+        assert!(config.whole_word);
+        assert!(config.whole_line);
+        assert!(config.regex);
+    }
+
+    #[test]
+    fn any_number_of_paths() {
+        let config = build(&["a", "b", "-", "c"], false).unwrap();
+        assert_eq!(config.paths, paths(&["b", "-", "c"]));
+
+        assert_eq!(build(&["a"], false).unwrap().paths, paths(&["-"]));
+        assert_eq!(build(&["-r", "a"], false).unwrap().paths, paths(&["."]));
+    }
+
+    #[test]
+    fn options_with_values() {
+        let config = build(
+            &[
+                "-r",
+                "--include",
+                "*.rs",
+                "--include=*.md",
+                "a",
+                "--exclude=target",
+                "b",
+            ],
+            false,
+        )
+        .unwrap();
+        assert!(config.recursive);
+        assert_eq!(config.include, vec!["*.rs", "*.md"]);
+        assert_eq!(config.exclude, vec!["target"]);
+        assert_eq!(config.patterns, vec!["a"]);
+        assert_eq!(config.paths, paths(&["b"]));
+
+        let error = build(&["a", "b", "--include"], false).unwrap_err();
+        assert_eq!(
+            error,
+            invalid("option '--include' requires a value".to_string())
+        );
+    }
+
+    #[test]
+    fn context_options() {
+        let config = build(&["-A", "2", "-B3", "a"], false).unwrap();
+        assert_eq!((config.before_context, config.after_context), (3, 2));
+
+        let config = build(&["-nC1", "a"], false).unwrap();
+        assert!(config.line_number);
+        assert_eq!((config.before_context, config.after_context), (1, 1));
+
+        let config = build(&["--context=4", "--after-context", "0", "a"], false).unwrap();
+        assert_eq!((config.before_context, config.after_context), (4, 0));
+
+        let error = build(&["-A", "x", "a"], false).unwrap_err();
+        assert_eq!(error, invalid("invalid context length 'x'".to_string()));
+        assert!(build(&["-C-1", "a"], false).is_err());
+    }
+
+    #[test]
+    fn output_options() {
+        let config = build(&["a"], false).unwrap();
+        assert_eq!(config.color, ColorChoice::Auto);
+        assert!(!config.only_matching);
+        assert!(!config.json);
+
+        let config = build(&["-o", "--json", "--color=never", "a"], false).unwrap();
+        assert_eq!(config.color, ColorChoice::Never);
+        assert!(config.only_matching);
+        assert!(config.json);
+
+        let config = build(&["--color", "always", "a"], false).unwrap();
+        assert_eq!(config.color, ColorChoice::Always);
+
+        let error = build(&["--color=sometimes", "a"], false).unwrap_err();
+        assert_eq!(
+            error,
+            invalid("invalid value 'sometimes' for '--color'".to_string())
+        );
+    }
+
+    #[test]
+    fn threads_and_sorting() {
+        let config = build(&["a"], false).unwrap();
+        assert_eq!(config.threads, 0);
+        assert_eq!(config.sort_by, None);
+
+        let config = build(&["-j4", "--sort=path", "a"], false).unwrap();
+        assert_eq!(config.threads, 4);
+        assert_eq!(config.sort_by, Some(SortBy::Path));
+
+        let config = build(&["--sort", "path", "--sort", "none", "a"], false).unwrap();
+        assert_eq!(config.sort_by, None);
+
+        let error = build(&["-j", "0", "a"], false).unwrap_err();
+        assert_eq!(error, invalid("invalid number of threads '0'".to_string()));
+        assert!(build(&["--threads=many", "a"], false).is_err());
+        assert!(build(&["--sort=size", "a"], false).is_err());
+    }
+
+    #[test]
+    fn binary_files() {
+        assert_eq!(build(&["a"], false).unwrap().binary_files, None);
+
+        let config = build(&["--binary-files=without-match", "a"], false).unwrap();
+        assert_eq!(config.binary_files, Some(BinaryFiles::WithoutMatch));
+
+        let config = build(&["--binary-files", "text", "a"], false).unwrap();
+        assert_eq!(config.binary_files, Some(BinaryFiles::Text));
+
+        assert!(build(&["--binary-files=maybe", "a"], false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_can_be_invalid_unicode() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let non_unicode = || OsString::from_vec(b"caf\xe9".to_vec());
+        let args = ["minigrep", "a"].map(OsString::from).into_iter();
+        let config = Config::build_with_env(args.chain([non_unicode()]), false).unwrap();
+        assert_eq!(config.paths, vec![PathBuf::from(non_unicode())]);
+
+        let args = ["minigrep".into(), non_unicode(), "b".into()];
+        let error = Config::build_with_env(args.into_iter(), false).unwrap_err();
+        assert_eq!(
+            error,
+            invalid("invalid Unicode in <query> 'caf\u{fffd}'".to_string())
+        );
+
+        let mut option = b"--include=".to_vec();
+        option.extend(non_unicode().into_vec());
+        let args = ["minigrep".into(), OsString::from_vec(option), "a".into()];
+        assert!(Config::build_with_env(args.into_iter(), false).is_err());
+
+        let args = [
+            "minigrep".into(),
+            "--include".into(),
+            non_unicode(),
+            "a".into(),
+        ];
+        assert!(Config::build_with_env(args.into_iter(), false).is_err());
+    }
+
+    #[test]
+    fn replace_options() {
+        let config = build(&["--replace", "$1", "(a)"], false).unwrap();
+        assert_eq!(config.replace.as_deref(), Some("$1"));
+        assert!(!config.in_place);
+
+        let config = build(&["--replace=b", "--in-place", "--dry-run", "a", "f"], false).unwrap();
+        assert!(config.in_place);
+        assert!(config.dry_run);
+
+        let error = build(&["--in-place", "a", "f"], false).unwrap_err();
+        assert_eq!(error, invalid("--in-place needs --replace".to_string()));
+        assert!(build(&["--replace=b", "--in-place", "-v", "a", "f"], false).is_err());
+        assert!(build(&["--replace=b", "--dry-run", "a", "f"], false).is_err());
+    }
+
+    #[test]
+    fn fuzzy() {
+        assert_eq!(build(&["a"], false).unwrap().fuzzy, None);
+        let config = build(&["--fuzzy", "2", "--sort=score", "a"], false).unwrap();
+        assert_eq!(config.fuzzy, Some(2));
+        assert_eq!(config.sort_by, Some(SortBy::Score));
+
+        let error = build(&["--fuzzy=-1", "a"], false).unwrap_err();
+        assert_eq!(error, invalid("invalid edit distance '-1'".to_string()));
+        assert!(build(&["--fuzzy=1", "-E", "a"], false).is_err());
+        assert!(build(&["--fuzzy=1", "-w", "a"], false).is_err());
+        assert!(build(&["--fuzzy=1", "-e", "a", "-e", "b"], false).is_err());
+
+        let error = build(&["--sort=score", "a"], false).unwrap_err();
+        assert_eq!(error, invalid("--sort=score needs --fuzzy".to_string()));
+        assert!(build(&["--fuzzy=1", "--sort=score", "-v", "a"], false).is_err());
+        assert!(build(&["--fuzzy=1", "--sort=score", "-C1", "a"], false).is_err());
+    }
+
+    #[test]
+    fn search_zip() {
+        assert!(!build(&["a"], false).unwrap().search_zip);
+        assert!(build(&["-zr", "a"], false).unwrap().search_zip);
+        assert!(build(&["--search-zip", "a"], false).unwrap().search_zip);
+
+        let error = build(&["-z", "--replace=b", "--in-place", "a", "f"], false).unwrap_err();
+        assert_eq!(
+            error,
+            invalid("--in-place can't be used with -z".to_string())
+        );
+    }
+
+    #[test]
+    fn several_patterns() {
+        let config = build(&["-e", "a", "--regexp=b", "-ec", "f", "g"], false).unwrap();
+        assert_eq!(config.patterns, vec!["a", "b", "c"]);
+        assert_eq!(config.paths, paths(&["f", "g"]));
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("patterns.txt");
+        fs::write(&file, "x\ny\n").unwrap();
+        let file = file.to_str().unwrap();
+        let config = build(&["-f", file, "-e", "z"], false).unwrap();
+        assert_eq!(config.patterns, vec!["x", "y", "z"]);
+        assert_eq!(config.paths, paths(&["-"]));
+
+        assert!(build(&["-f", "no/such/file", "a"], false).is_err());
+
+        // Smart case looks at all of them.
+        assert!(
+            !build(&["-S", "-e", "a", "-e", "B"], false)
+                .unwrap()
+                .ignore_case
+        );
+    }
+
+    #[test]
+    fn several_patterns_match_at_once() {
+        let config = build(&["-e", "cat", "-e", "dog", "-w"], false).unwrap();
+        let matcher = config.matcher().unwrap();
+        assert!(matcher.is_match(b"hot dog"));
+        assert!(!matcher.is_match(b"dogs and cats"));
+
+        let config = build(&["-Ex", "-e", "a+", "-e", "b+"], false).unwrap();
+        let matcher = config.matcher().unwrap();
+        assert!(matcher.is_match(b"bbb"));
+        assert!(!matcher.is_match(b"aabb"));
+
+        let config = build(&["-i", "-e", "STRASSE", "-e", "x"], false).unwrap();
+        assert!(config.matcher().unwrap().is_match("straße".as_bytes()));
+
+        let mut config = build(&["a"], false).unwrap();
+        config.patterns.clear();
+        assert!(!config.matcher().unwrap().is_match(b"a"));
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        let config = build(&["-n", "--", "-v", "--weird-name"], false).unwrap();
+        assert!(config.line_number);
+        assert!(!config.invert_match);
+        assert_eq!(config.patterns, vec!["-v"]);
+        assert_eq!(config.paths, paths(&["--weird-name"]));
+    }
+
+    #[test]
+    fn flags_take_precedence_over_ignore_case_env() {
+        assert!(build(&["a", "b"], true).unwrap().ignore_case);
+        assert!(build(&["-i", "a", "b"], false).unwrap().ignore_case);
+        assert!(
+            !build(&["--no-ignore-case", "a", "b"], true)
+                .unwrap()
+                .ignore_case
+        );
+        assert!(
+            build(&["--no-ignore-case", "-i", "a", "b"], false)
+                .unwrap()
+                .ignore_case
+        );
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(build(&["-S", "rust", "f"], false).unwrap().ignore_case);
+        assert!(!build(&["-S", "Rust", "f"], false).unwrap().ignore_case);
+        assert!(!build(&["-S", "ПРИВЕТ", "f"], true).unwrap().ignore_case);
+        assert!(
+            build(&["-S", "-i", "Rust", "f"], false)
+                .unwrap()
+                .ignore_case
+        );
+        assert!(
+            !build(&["--no-ignore-case", "-S", "rust", "f"], false)
+                .unwrap()
+                .ignore_case
+        );
+    }
+
+    #[test]
+    fn help_and_version() {
+        assert_eq!(build(&["--help"], false).unwrap_err(), ConfigError::Help);
+        assert_eq!(
+            build(&["a", "-h", "b"], false).unwrap_err(),
+            ConfigError::Help
+        );
+        assert_eq!(build(&["-V"], false).unwrap_err(), ConfigError::Version);
+    }
+
+    #[test]
+    fn clear_errors() {
+        let error = build(&["-q", "a", "b"], false).unwrap_err();
+        assert_eq!(error, invalid("unknown option '-q'".to_string()));
+        assert!(error.to_string().contains("--help"));
+
+        let error = build(&["--frobnicate", "a", "b"], false).unwrap_err();
+        assert_eq!(error, invalid("unknown option '--frobnicate'".to_string()));
+
+        let error = build(&["--count=3", "a", "b"], false).unwrap_err();
+        assert_eq!(
+            error,
+            invalid("option '--count' doesn't take a value".to_string())
+        );
+
+        let error = build(&[], false).unwrap_err();
+        assert_eq!(error, invalid("missing <query> argument".to_string()));
+    }
+
+    #[test]
+    fn matcher_honours_word_and_line_flags() {
+        let config = build(&["-w", "cat", "f"], false).unwrap();
+        let matcher = config.matcher().unwrap();
+        assert!(matcher.is_match(b"the cat sat"));
+        assert!(!matcher.is_match(b"concatenate"));
+
+        let config = build(&["-x", "cat", "f"], false).unwrap();
+        let matcher = config.matcher().unwrap();
+        assert!(matcher.is_match(b"cat"));
+        assert!(!matcher.is_match(b"cats"));
+
+        let config = build(&["-xE", "ab|abc", "f"], false).unwrap();
+        assert!(config.matcher().unwrap().is_match(b"abc"));
+
+        let config = build(&["-wE", "ab|abc", "f"], false).unwrap();
+        let matcher = config.matcher().unwrap();
+        assert_eq!(matcher.find_at(b"abc", 0), Some(0..3));
+        assert_eq!(matcher.find_at(b"x ab c", 0), Some(2..4));
+        assert!(!matcher.is_match(b"abcd"));
+
+        let config = build(&["-w", "-E", "-e", "ab", "-e", "abc", "f"], false).unwrap();
+        assert!(config.matcher().unwrap().is_match(b"abc"));
+
+        let config = build(&["-ix", "CAT", "f"], false).unwrap();
+        assert!(config.matcher().unwrap().is_match(b"Cat"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Picking the lines to show around each selected line, for `-A`/`-B`/`-C`.
+
+use std::collections::VecDeque;
+
+/// Keeps track of which lines are context for a selected line, as lines are fed to it in order.
+///
+/// Every line is emitted at most once, so the windows of selected lines that are close to each
+/// other merge on their own.
+pub struct ContextWindow<T> {
+    before: usize,
+    after: usize,
+    /// The last few lines that weren't emitted, in case the next one is selected.
+    buffered: VecDeque<T>,
+    /// How many more lines to emit as context for the last selected line.
+    after_left: usize,
+}
+
+impl<T> ContextWindow<T> {
+    pub fn new(before: usize, after: usize) -> ContextWindow<T> {
+        ContextWindow {
+            before,
+            after,
+            buffered: VecDeque::with_capacity(before),
+            after_left: 0,
+        }
+    }
+
+    /// Whether this window ever shows any context lines.
+    pub fn shows_context(&self) -> bool {
+        self.before > 0 || self.after > 0
+    }
+
+    /// Whether `push` would do anything with the next line, so callers can avoid preparing lines
+    /// that would just be thrown away.
+    pub fn wants(&self, selected: bool) -> bool {
+        selected || self.after_left > 0 || self.before > 0
+    }
+
+    /// Feeds the next line in, calling `emit` for each line that should be shown (this one or
+    /// buffered ones), along with whether it's a context line.
+    pub fn push(&mut self, line: T, selected: bool, mut emit: impl FnMut(T, bool)) {
+        if selected {
+            for line in self.buffered.drain(..) {
+                emit(line, true);
+            }
+
+            emit(line, false);
+            self.after_left = self.after;
+        } else if self.after_left > 0 {
+            emit(line, true);
+            self.after_left -= 1;
+        } else if self.before > 0 {
+            if self.buffered.len() == self.before {
+                self.buffered.pop_front();
+            }
+
+            self.buffered.push_back(line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(selected: &[usize], len: usize, before: usize, after: usize) -> Vec<(usize, bool)> {
+        let mut window = ContextWindow::new(before, after);
+        let mut emitted = Vec::new();
+        for line in 0..len {
+            window.push(line, selected.contains(&line), |line, context| {
+                emitted.push((line, context))
+            });
+        }
+
+        emitted
+    }
+
+    #[test]
+    fn no_context() {
+        assert_eq!(window(&[1, 4], 6, 0, 0), vec![(1, false), (4, false)]);
+    }
+
+    #[test]
+    fn before_and_after() {
+        assert_eq!(
+            window(&[5], 10, 2, 1),
+            vec![(3, true), (4, true), (5, false), (6, true)]
+        );
+
+        // Windows are cut short at the start and end of the input.
+        assert_eq!(
+            window(&[0, 9], 10, 2, 2),
+            vec![
+                (0, false),
+                (1, true),
+                (2, true),
+                (7, true),
+                (8, true),
+                (9, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn overlapping_windows_merge() {
+        assert_eq!(
+            window(&[2, 4], 10, 1, 1),
+            vec![(1, true), (2, false), (3, true), (4, false), (5, true)]
+        );
+
+        // A selected line inside another one's after-context isn't emitted twice.
+        assert_eq!(
+            window(&[2, 3], 10, 2, 2),
+            vec![
+                (0, true),
+                (1, true),
+                (2, false),
+                (3, false),
+                (4, true),
+                (5, true)
+            ]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Searching compressed files as if they weren't, for `-z`.
+
+use std::io::{self, BufRead, BufReader};
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+
+/// The compression formats `decompress` knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Format {
+    /// Tells which format some data is compressed with from the "magic bytes" it starts with, so
+    /// compressed files are found whatever their extension is.
+    fn detect(start: &[u8]) -> Option<Format> {
+        if start.starts_with(&[0x1f, 0x8b]) {
+            Some(Format::Gzip)
+        } else if start.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Format::Zstd)
+        } else if Format::is_bzip2(start) {
+            Some(Format::Bzip2)
+        } else {
+            None
+        }
+    }
+
+    /// Text can easily start with "BZh" too, so this also checks the block size digit after it and
+    /// the magic number of the first block (or of the end of the stream, if it's empty).
+    fn is_bzip2(start: &[u8]) -> bool {
+        const BLOCK: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+        const END: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+        match start {
+            [b'B', b'Z', b'h', b'1'..=b'9', magic @ ..] => {
+                magic.starts_with(&BLOCK) || magic.starts_with(&END)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// If `reader` holds compressed data, returns a reader that decompresses it as it's read.
+/// Otherwise returns `reader` as it is.
+///
+/// Files made of several compressed streams one after the other (like `cat a.gz b.gz` makes) are
+/// read as a whole.
+///
+/// # Errors
+///
+/// Fails if reading the start of `reader` fails. If the data turns out to be corrupt, reading from
+/// the returned reader fails.
+pub fn decompress<'a>(mut reader: Box<dyn BufRead + 'a>) -> io::Result<Box<dyn BufRead + 'a>> {
+    let reader: Box<dyn BufRead + 'a> = match Format::detect(reader.fill_buf()?) {
+        Some(Format::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Some(Format::Zstd) => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        Some(Format::Bzip2) => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+        None => reader,
+    };
+
+    Ok(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+
+    const TEXT: &[u8] = b"first line\nsecond line\n";
+
+    fn decompressed(data: Vec<u8>) -> Vec<u8> {
+        let mut reader = decompress(Box::new(io::Cursor::new(data))).unwrap();
+        let mut text = Vec::new();
+        reader.read_to_end(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn detects_formats() {
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(TEXT).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(Format::detect(&gzip), Some(Format::Gzip));
+        assert_eq!(decompressed(gzip), TEXT);
+
+        let zstd = zstd::encode_all(TEXT, 0).unwrap();
+        assert_eq!(Format::detect(&zstd), Some(Format::Zstd));
+        assert_eq!(decompressed(zstd), TEXT);
+
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(TEXT).unwrap();
+        let bzip2 = bzip2.finish().unwrap();
+        assert_eq!(Format::detect(&bzip2), Some(Format::Bzip2));
+        assert_eq!(decompressed(bzip2), TEXT);
+
+        assert_eq!(Format::detect(TEXT), None);
+        assert_eq!(decompressed(TEXT.to_vec()), TEXT);
+    }
+
+    #[test]
+    fn text_starting_with_bzip2_magic_is_not_decompressed() {
+        let empty = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        let empty = empty.finish().unwrap();
+        assert_eq!(Format::detect(&empty), Some(Format::Bzip2));
+        assert_eq!(decompressed(empty), b"");
+
+        for text in [&b"BZhello world\n"[..], b"BZh9 blocks\n", b"BZh"] {
+            assert_eq!(Format::detect(text), None);
+            assert_eq!(decompressed(text.to_vec()), text);
+        }
+    }
+
+    #[test]
+    fn reads_concatenated_streams() {
+        let mut data = Vec::new();
+        for text in [&b"one\n"[..], b"two\n"] {
+            let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            gzip.write_all(text).unwrap();
+            data.extend(gzip.finish().unwrap());
+        }
+
+        assert_eq!(decompressed(data), b"one\ntwo\n");
+    }
+
+    #[test]
+    fn corrupt_data_fails_when_read() {
+        let mut reader = decompress(Box::new(&[0x1f, 0x8b, 0, 0, 0][..])).unwrap();
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Making the replacements in the files themselves, for `--in-place`, or showing them as a diff
+//! with `--dry-run`.
+
+use std::{
+    fs,
+    io::{self, Write},
+    ops::Range,
+    path::Path,
+};
+
+use memchr::memchr_iter;
+use tempfile::NamedTempFile;
+
+use crate::{matcher::replace_all, stream::next_matching_line, Matcher};
+
+/// How many unchanged lines a diff shows around each change, like `diff -u` does.
+const DIFF_CONTEXT: usize = 3;
+
+/// A line that the replacements change.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change {
+    /// The index of the line, starting from 0.
+    index: usize,
+    /// Where the line is in the original text, with its line ending.
+    old: Range<usize>,
+    /// What the line becomes, with its line ending.
+    new: Vec<u8>,
+}
+
+/// Finds the lines of `contents` that replacing every match of `matcher` with `replacement`
+/// changes.
+pub fn changes(matcher: &dyn Matcher, contents: &[u8], replacement: &str) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut start = 0;
+    let mut index = 0;
+    while let Some((line, next)) = next_matching_line(matcher, contents, start) {
+        index += memchr_iter(b'\n', &contents[start..line.start]).count();
+        let mut new = replace_all(matcher, &contents[line.clone()], replacement);
+        new.extend_from_slice(&contents[line.end..next]);
+
+        // Replacing a match with the same text doesn't change anything.
+        if new != contents[line.start..next] {
+            changes.push(Change {
+                index,
+                old: line.start..next,
+                new,
+            });
+        }
+
+        index += 1;
+        start = next;
+    }
+
+    changes
+}
+
+/// Returns `contents` with `changes` (found in it by `changes`) made.
+pub fn apply(contents: &[u8], changes: &[Change]) -> Vec<u8> {
+    let mut edited = Vec::with_capacity(contents.len());
+    let mut copied = 0;
+    for change in changes {
+        edited.extend_from_slice(&contents[copied..change.old.start]);
+        edited.extend_from_slice(&change.new);
+        copied = change.old.end;
+    }
+
+    edited.extend_from_slice(&contents[copied..]);
+    edited
+}
+
+/// Replaces the contents of the file at `path` with `contents`.
+///
+/// The new contents are written to a temporary file next to the original first, which is then
+/// renamed over it, so the file is never left half written (even if we're killed halfway
+/// through). The file keeps its permissions, and if `path` is a symbolic link, the file it points
+/// to is the one replaced.
+pub fn write_in_place(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let dir = path.parent().unwrap_or(Path::new("/"));
+
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(contents)?;
+    file.as_file()
+        .set_permissions(fs::metadata(&path)?.permissions())?;
+    file.as_file().sync_all()?;
+    file.persist(&path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Writes `changes` to `contents` out as a unified diff (like `diff -u` prints), `name` being
+/// what the file is called in it.
+pub fn write_diff(
+    out: &mut impl Write,
+    name: &str,
+    contents: &[u8],
+    changes: &[Change],
+) -> io::Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "--- {name}")?;
+    writeln!(out, "+++ {name}")?;
+
+    let lines: Vec<_> = contents.split_inclusive(|&byte| byte == b'\n').collect();
+    // How many more lines the new file has before the current hunk, since a replacement can add
+    // line breaks.
+    let mut added_lines: isize = 0;
+
+    // Changes close enough for their context to touch go in the same hunk.
+    for hunk in changes.chunk_by(|a, b| b.index - a.index <= 2 * DIFF_CONTEXT + 1) {
+        let start = hunk[0].index.saturating_sub(DIFF_CONTEXT);
+        let end = (hunk[hunk.len() - 1].index + 1 + DIFF_CONTEXT).min(lines.len());
+        let new_lines: usize = hunk.iter().map(|change| line_count(&change.new)).sum();
+        let new_len = end - start - hunk.len() + new_lines;
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            hunk_range(start, end - start),
+            hunk_range(start.saturating_add_signed(added_lines), new_len)
+        )?;
+        added_lines += new_lines as isize - hunk.len() as isize;
+
+        let mut hunk = hunk.iter().peekable();
+        let mut index = start;
+        while index < end {
+            // Like `diff`, runs of changed lines are shown all removed first, then all added.
+            let mut run = Vec::new();
+            while let Some(change) = hunk.next_if(|change| change.index == index + run.len()) {
+                run.push(change);
+            }
+
+            if run.is_empty() {
+                write_diff_line(out, b' ', lines[index])?;
+                index += 1;
+                continue;
+            }
+
+            for change in &run {
+                write_diff_line(out, b'-', &contents[change.old.clone()])?;
+            }
+
+            for change in &run {
+                for line in change.new.split_inclusive(|&byte| byte == b'\n') {
+                    write_diff_line(out, b'+', line)?;
+                }
+            }
+
+            index += run.len();
+        }
+    }
+
+    Ok(())
+}
+
+fn line_count(text: &[u8]) -> usize {
+    text.split_inclusive(|&byte| byte == b'\n').count()
+}
+
+/// Formats the lines from `start` (counting from 0) on, for the header of a hunk.
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        // An empty range names the line before it.
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{len}", start + 1),
+    }
+}
+
+fn write_diff_line(out: &mut impl Write, prefix: u8, line: &[u8]) -> io::Result<()> {
+    out.write_all(&[prefix])?;
+    out.write_all(line)?;
+    if !line.ends_with(b"\n") {
+        out.write_all(b"\n\\ No newline at end of file\n")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LiteralMatcher, RegexMatcher};
+
+    const CONTENTS: &[u8] = b"\
+one
+two
+three
+four
+five
+six
+seven
+eight
+nine
+ten
+eleven
+twelve";
+
+    fn diff(matcher: &dyn Matcher, contents: &[u8], replacement: &str) -> String {
+        let changes = changes(matcher, contents, replacement);
+        let mut out = Vec::new();
+        write_diff(&mut out, "f.txt", contents, &changes).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn applies_changes() {
+        let matcher = RegexMatcher::new("^t(\\w)", false).unwrap();
+        let changes = changes(&matcher, CONTENTS, "T$1");
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[0].index, 1);
+        assert_eq!(changes[0].new, b"Two\n");
+
+        let edited = String::from_utf8(apply(CONTENTS, &changes)).unwrap();
+        assert_eq!(edited.lines().filter(|l| l.starts_with('T')).count(), 4);
+        assert!(edited.ends_with("\nTwelve"));
+
+        // Replacing a match with itself isn't a change.
+        assert!(super::changes(&LiteralMatcher::new("o"), CONTENTS, "o").is_empty());
+    }
+
+    #[test]
+    fn diffs_nearby_changes_in_one_hunk() {
+        let matcher = LiteralMatcher::new("t");
+        assert_eq!(
+            diff(&matcher, b"a\nbat\nc\n", "T"),
+            "--- f.txt\n+++ f.txt\n@@ -1,3 +1,3 @@\n a\n-bat\n+baT\n c\n"
+        );
+
+        let matcher = RegexMatcher::new("^(two|three|ten)$", false).unwrap();
+        assert_eq!(
+            diff(&matcher, CONTENTS, "2"),
+            "\
+--- f.txt
++++ f.txt
+@@ -1,12 +1,12 @@
+ one
+-two
+-three
++2
++2
+ four
+ five
+ six
+ seven
+ eight
+ nine
+-ten
++2
+ eleven
+ twelve
+\\ No newline at end of file
+"
+        );
+    }
+
+    #[test]
+    fn diffs_distant_changes_in_separate_hunks() {
+        let matcher = RegexMatcher::new("^(one|twelve)$", false).unwrap();
+        assert_eq!(
+            diff(&matcher, CONTENTS, "1\n1"),
+            "\
+--- f.txt
++++ f.txt
+@@ -1,4 +1,5 @@
+-one
++1
++1
+ two
+ three
+ four
+@@ -9,4 +10,5 @@
+ nine
+ ten
+ eleven
+-twelve
+\\ No newline at end of file
++1
++1
+\\ No newline at end of file
+"
+        );
+    }
+
+    #[test]
+    fn writes_files_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("f.txt");
+        fs::write(&path, "old\n").unwrap();
+        write_in_place(&path, b"new\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new\n");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
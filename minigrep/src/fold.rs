@@ -0,0 +1,203 @@
+//! Unicode case folding, for case-insensitive matching.
+//!
+//! Folding maps every character to a canonical lowercase form, so that two strings that only
+//! differ in case fold to the same text. Unlike plain lowercasing, characters with several
+//! lowercase spellings all fold to the same one (`ς` and `σ`, `ſ` and `s`), and `ß` folds to
+//! `ss` so that "STRASSE" and "straße" match.
+
+use std::ops::Range;
+
+use memchr::memmem;
+
+/// Appends the case folded form of `c` to `out`.
+pub fn fold_char(c: char, out: &mut String) {
+    out.extend(folded_chars(c));
+}
+
+/// Returns the characters `c` folds to, which are never more than 3.
+pub fn folded_chars(c: char) -> impl Iterator<Item = char> {
+    let mut folded = [c; 3];
+    let len = match c {
+        // The ASCII fast path, since it's by far the most common case.
+        'A'..='Z' => {
+            folded[0] = c.to_ascii_lowercase();
+            1
+        }
+        c if c.is_ascii() => 1,
+        'ß' | 'ẞ' => {
+            folded = ['s'; 3];
+            2
+        }
+        'ς' => {
+            folded[0] = 'σ';
+            1
+        }
+        'ſ' => {
+            folded[0] = 's';
+            1
+        }
+        c => {
+            let mut len = 0;
+            for (slot, lower) in folded.iter_mut().zip(c.to_lowercase()) {
+                *slot = lower;
+                len += 1;
+            }
+            len
+        }
+    };
+
+    folded.into_iter().take(len)
+}
+
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        fold_char(c, &mut folded);
+    }
+
+    folded
+}
+
+/// A case folded copy of some text that remembers where each of its bytes came from, so matches
+/// found in it can be mapped back to the original.
+///
+/// The text doesn't have to be valid UTF-8: invalid bytes are copied over as they are.
+pub struct Folded {
+    text: Vec<u8>,
+    /// For each byte of `text`, the range of the original character it was folded from.
+    origins: Vec<Range<usize>>,
+    /// Where the original text ends.
+    end: usize,
+}
+
+impl Folded {
+    /// Folds `text`, whose first byte is at `offset` in the original.
+    pub fn new(text: &[u8], offset: usize) -> Folded {
+        let mut folded = String::with_capacity(text.len());
+        let mut bytes = Vec::with_capacity(text.len());
+        let mut origins = Vec::with_capacity(text.len());
+        let mut start = offset;
+        for chunk in text.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                folded.clear();
+                fold_char(c, &mut folded);
+                bytes.extend_from_slice(folded.as_bytes());
+                origins.resize(bytes.len(), start..start + c.len_utf8());
+                start += c.len_utf8();
+            }
+
+            for &byte in chunk.invalid() {
+                bytes.push(byte);
+                origins.push(start..start + 1);
+                start += 1;
+            }
+        }
+
+        Folded {
+            text: bytes,
+            origins,
+            end: offset + text.len(),
+        }
+    }
+
+    /// Finds the first occurrence of the (already folded) `needle`, returning its range in the
+    /// original text.
+    ///
+    /// A character that folds to several (like `ß` to `ss`) can only match as a whole, so
+    /// searching for "s" doesn't find anything in "ß".
+    pub fn find(&self, needle: &str) -> Option<Range<usize>> {
+        let needle = needle.as_bytes();
+        self.find_with(|text, from| {
+            let start = from + memmem::find(&text[from..], needle)?;
+            Some(start..start + needle.len())
+        })
+    }
+
+    /// Like `find`, but `search` does the searching: given the folded text and where to start, it
+    /// returns the range of the next occurrence of whatever is being looked for (which has to be
+    /// valid UTF-8).
+    pub fn find_with(
+        &self,
+        mut search: impl FnMut(&[u8], usize) -> Option<Range<usize>>,
+    ) -> Option<Range<usize>> {
+        let mut from = 0;
+        while let Some(Range { start, end }) = search(&self.text, from) {
+            if self.is_char_boundary(start) && self.is_char_boundary(end) {
+                let original_start = self.origins.get(start).map_or(self.end, |o| o.start);
+                let original_end = if end == start {
+                    original_start
+                } else {
+                    self.origins[end - 1].end
+                };
+
+                return Some(original_start..original_end);
+            }
+
+            // Occurrences are valid UTF-8, so they can't start in the middle of a character: the
+            // next byte is as good as the next character.
+            from = start + 1;
+        }
+
+        None
+    }
+
+    /// Whether `index` in the folded text falls between the foldings of two original characters.
+    fn is_char_boundary(&self, index: usize) -> bool {
+        index == 0 || index == self.text.len() || self.origins[index - 1] != self.origins[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_beyond_ascii() {
+        assert_eq!(fold("Hello, World"), "hello, world");
+        assert_eq!(fold("STRASSE"), fold("straße"));
+        assert_eq!(fold("Straẞe"), "strasse");
+        assert_eq!(fold("ΟΔΥΣΣΕΥΣ"), fold("Οδυσσευς"));
+        assert_eq!(fold("ПРИВЕТ"), "привет");
+        assert_eq!(fold("ſtop"), "stop");
+        // The Kelvin sign.
+        assert_eq!(fold("\u{212a}"), "k");
+    }
+
+    #[test]
+    fn maps_matches_back_to_the_original() {
+        let text = "Die Straße ist lang";
+        let folded = Folded::new(text.as_bytes(), 0);
+        let range = folded.find(&fold("STRASSE")).unwrap();
+        assert_eq!(&text[range], "Straße");
+
+        let folded = Folded::new(&text.as_bytes()[4..], 4);
+        assert_eq!(folded.find("lang"), Some(16..20));
+        assert_eq!(folded.find("ss"), Some(8..10));
+    }
+
+    #[test]
+    fn expanded_characters_only_match_whole() {
+        let folded = Folded::new("maß".as_bytes(), 0);
+        assert_eq!(folded.find("s"), None);
+        assert_eq!(folded.find("ss"), Some(2..4));
+        assert_eq!(folded.find("mass"), Some(0..4));
+
+        // If an occurrence splits a character, the search goes on to the next one.
+        let folded = Folded::new("ßs".as_bytes(), 0);
+        assert_eq!(folded.find("s"), Some(2..3));
+    }
+
+    #[test]
+    fn keeps_invalid_bytes() {
+        let folded = Folded::new(b"\xffR\xc3ST\xe2\x82", 0);
+        assert_eq!(folded.find("rst"), None);
+        assert_eq!(folded.find("st"), Some(3..5));
+        assert_eq!(folded.find("r"), Some(1..2));
+    }
+
+    #[test]
+    fn empty_needle() {
+        assert_eq!(Folded::new(b"abc", 3).find(""), Some(3..3));
+        assert_eq!(Folded::new(b"", 3).find(""), Some(3..3));
+    }
+}
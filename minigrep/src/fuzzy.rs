@@ -0,0 +1,462 @@
+//! Approximate matching, for `--fuzzy`.
+//!
+//! The edit (or Levenshtein) distance between two strings is how many characters have to be
+//! inserted, deleted or substituted to turn one into the other. Finding the parts of a text within
+//! some distance of a pattern is done with Myers' bit-parallel algorithm: instead of filling in the
+//! whole matrix of distances between every prefix of the pattern and the text, it keeps a single
+//! column of it in the bits of a few words, and works out the next one with a handful of bitwise
+//! operations. That makes searching take time linear in the length of the text, for patterns up
+//! to 64 characters long (longer ones are split into blocks of 64, and take that many times more).
+
+use std::{
+    collections::{HashMap, VecDeque},
+    iter,
+    ops::Range,
+    str,
+};
+
+use crate::fold;
+
+/// How many characters of the pattern fit in a block.
+const BLOCK_LEN: usize = 64;
+
+/// A pattern prepared for searching with Myers' algorithm.
+///
+/// Distances are counted in characters. The text doesn't have to be valid UTF-8: an invalid byte
+/// counts as a character of its own, which never matches any character of the pattern.
+pub struct Pattern {
+    chars: Vec<char>,
+    /// Whether both the pattern and the text are case folded (see the `fold` module).
+    ignore_case: bool,
+    /// For each ASCII character, which positions of the pattern hold it, as one bit mask per
+    /// block.
+    ascii: Vec<u64>,
+    /// The same for the rest of the characters in the pattern.
+    other: HashMap<char, Vec<u64>>,
+    /// The masks for characters that aren't in the pattern at all.
+    nowhere: Vec<u64>,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, ignore_case: bool) -> Pattern {
+        let chars: Vec<_> = if ignore_case {
+            fold::fold(pattern).chars().collect()
+        } else {
+            pattern.chars().collect()
+        };
+        let blocks = chars.len().div_ceil(BLOCK_LEN);
+        let mut ascii = vec![0; 128 * blocks];
+        let mut other = HashMap::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let masks: &mut [u64] = if c.is_ascii() {
+                &mut ascii[c as usize * blocks..][..blocks]
+            } else {
+                other.entry(c).or_insert_with(|| vec![0; blocks])
+            };
+            masks[i / BLOCK_LEN] |= 1 << (i % BLOCK_LEN);
+        }
+
+        Pattern {
+            chars,
+            ignore_case,
+            ascii,
+            other,
+            nowhere: vec![0; blocks],
+        }
+    }
+
+    /// Whether some part of `text` is at most `max` edits away from the pattern.
+    pub fn is_match(&self, text: &[u8], max: usize) -> bool {
+        self.best_distance(text, max) <= max
+    }
+
+    /// Returns the smallest distance between the pattern and any part of `text`, if it's at most
+    /// `max`.
+    pub fn distance(&self, text: &[u8], max: usize) -> Option<usize> {
+        Some(self.best_distance(text, 0)).filter(|&distance| distance <= max)
+    }
+
+    /// Finds the first part of `text` from `start` on that's at most `max` edits away from the
+    /// pattern, returning its range and distance.
+    ///
+    /// Once a good enough match is found, it's extended for as long as that makes it closer, so
+    /// that searching for "hello" in "hello" with a `max` of 1 finds all of it rather than just
+    /// "hell". The text after the match is never looked at, so finding every match in a line
+    /// takes time linear in its length.
+    ///
+    /// If the match starts or ends in the middle of a character that folds to several (like `ß`
+    /// to `ss`), the range includes all of it.
+    pub fn find(&self, text: &[u8], start: usize, max: usize) -> Option<(Range<usize>, usize)> {
+        let mut chars = self.folded_chars(text, start).peekable();
+        // Only the characters the match could be made of are kept, which is at most `max` more
+        // than the pattern has.
+        let window_len = self.chars.len().saturating_add(max);
+        let mut window = VecDeque::new();
+        let mut keep = |unit| {
+            window.push_back(unit);
+            if window.len() > window_len {
+                window.pop_front();
+            }
+        };
+
+        let mut column = Column::new(self);
+        while column.distance > max {
+            let (range, c) = chars.next()?;
+            column.advance(c);
+            keep((range, c));
+        }
+
+        let mut distance = column.distance;
+        while let Some((range, c)) = chars.next_if(|&(_, c)| column.try_advance(c, distance)) {
+            distance = column.distance;
+            keep((range, c));
+        }
+
+        let window = Vec::from(window);
+        let text: Vec<_> = window.iter().map(|&(_, c)| c).collect();
+        let len = self.match_len(&text, distance);
+        let end = window.last().map_or(start, |(range, _)| range.end);
+        let start = window[window.len() - len..]
+            .first()
+            .map_or(end, |(range, _)| range.start);
+        Some((start..end, distance))
+    }
+
+    /// The smallest distance between the pattern and any part of `text`, stopping early once it's
+    /// down to `good_enough`.
+    fn best_distance(&self, text: &[u8], good_enough: usize) -> usize {
+        let mut column = Column::new(self);
+        let mut best = column.distance;
+        for (_, c) in self.folded_chars(text, 0) {
+            if best <= good_enough {
+                break;
+            }
+
+            column.advance(c);
+            best = best.min(column.distance);
+        }
+
+        best
+    }
+
+    /// How many characters from the end of `text` a match that ends there (and is `distance`
+    /// edits away from the pattern) takes up.
+    ///
+    /// Myers' algorithm only says where matches end, so this works out where it starts by filling
+    /// in the matrix of distances the slow way, backwards from the end. That's only needed for the
+    /// matches themselves, which can't be more than `distance` characters longer than the pattern.
+    /// If several starts are just as close, the earliest is picked, as substituting a character
+    /// looks more like a match than skipping it.
+    fn match_len(&self, text: &[Option<char>], distance: usize) -> usize {
+        let text = &text[text.len().saturating_sub(self.chars.len() + distance)..];
+
+        // `row[j]` is the distance between the end of the pattern (one more character of it each
+        // time around) and the last `j` characters of the text.
+        let mut row: Vec<_> = (0..=text.len()).collect();
+        for (i, &p) in self.chars.iter().rev().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for j in 1..=text.len() {
+                let substituted = diagonal + usize::from(text[text.len() - j] != Some(p));
+                diagonal = row[j];
+                row[j] = substituted.min(row[j] + 1).min(row[j - 1] + 1);
+            }
+        }
+
+        (0..=text.len()).rev().min_by_key(|&j| row[j]).unwrap_or(0)
+    }
+
+    /// The characters of `text` from `start` on, case folded if ignoring case, along with the
+    /// range of the original character each one comes from.
+    fn folded_chars<'t>(
+        &self,
+        text: &'t [u8],
+        start: usize,
+    ) -> impl Iterator<Item = (Range<usize>, Option<char>)> + 't {
+        let ignore_case = self.ignore_case;
+        chars(text, start).flat_map(move |(range, c)| {
+            let folded = match c {
+                Some(c) if ignore_case => Some(fold::folded_chars(c)),
+                _ => None,
+            };
+
+            // Characters that aren't folded stand for themselves.
+            let unfolded = folded.is_none().then_some(c);
+            let chars = folded.into_iter().flatten().map(Some).chain(unfolded);
+            chars.map(move |c| (range.clone(), c))
+        })
+    }
+
+    fn masks(&self, c: Option<char>) -> &[u64] {
+        let blocks = self.nowhere.len();
+        match c {
+            Some(c) if c.is_ascii() => &self.ascii[c as usize * blocks..][..blocks],
+            Some(c) => self.other.get(&c).map_or(&self.nowhere, Vec::as_slice),
+            None => &self.nowhere,
+        }
+    }
+}
+
+/// A column of the matrix of distances between every prefix of a pattern and the parts of the
+/// text ending at some character.
+///
+/// Next to each other, two distances in a column never differ by more than 1, so rather than the
+/// distances themselves, it keeps a bit per row for whether it's 1 more than the row above
+/// (`plus`), and another for whether it's 1 less (`minus`). Only the distance in the last row,
+/// for the whole pattern, is kept as a number.
+struct Column<'p> {
+    pattern: &'p Pattern,
+    plus: Vec<u64>,
+    minus: Vec<u64>,
+    distance: usize,
+}
+
+impl<'p> Column<'p> {
+    /// The column for before the start of the text, where the distance in each row is the number
+    /// of characters of the pattern it's for.
+    fn new(pattern: &'p Pattern) -> Column<'p> {
+        let blocks = pattern.nowhere.len();
+        Column {
+            pattern,
+            plus: vec![!0; blocks],
+            minus: vec![0; blocks],
+            distance: pattern.chars.len(),
+        }
+    }
+
+    /// Moves on to the column for the next character of the text.
+    fn advance(&mut self, c: Option<char>) {
+        let masks = self.pattern.masks(c);
+        // A match can start anywhere in the text, so the distance in the row above the pattern
+        // stays 0.
+        let mut carry = 0;
+        let blocks = self.plus.iter_mut().zip(&mut self.minus).zip(masks);
+        for (block, ((plus, minus), &eq)) in blocks.enumerate() {
+            let last_row = if block + 1 == masks.len() {
+                (self.pattern.chars.len() - 1) % BLOCK_LEN
+            } else {
+                BLOCK_LEN - 1
+            };
+
+            carry = advance_block(plus, minus, eq, carry, 1 << last_row);
+        }
+
+        self.distance = self.distance.saturating_add_signed(carry);
+    }
+
+    /// Moves on to the column for `c` if that makes the distance smaller than `than`, returning
+    /// whether it did. If it didn't, the column is left in a state that's no use any more.
+    fn try_advance(&mut self, c: Option<char>, than: usize) -> bool {
+        self.advance(c);
+        self.distance < than
+    }
+}
+
+/// Works out the next column for a block of rows, given which of them hold the text's next
+/// character (`eq`) and how the distance changes from one column to the next in the row above the
+/// block (`carry`, which is -1, 0 or 1). Returns how it changes in the row `last_row` is the bit
+/// of.
+///
+/// This is the heart of Myers' algorithm, with blocks chained together like Hyyrö describes.
+fn advance_block(plus: &mut u64, minus: &mut u64, eq: u64, carry: isize, last_row: u64) -> isize {
+    let xv = eq | *minus;
+    let eq = if carry < 0 { eq | 1 } else { eq };
+    let xh = ((eq & *plus).wrapping_add(*plus) ^ *plus) | eq;
+    // How the distances change from the current column to the next one, along each row.
+    let mut row_plus = *minus | !(xh | *plus);
+    let mut row_minus = *plus & xh;
+
+    let carry_out = if row_plus & last_row != 0 {
+        1
+    } else if row_minus & last_row != 0 {
+        -1
+    } else {
+        0
+    };
+
+    row_plus <<= 1;
+    row_minus <<= 1;
+    if carry < 0 {
+        row_minus |= 1;
+    } else if carry > 0 {
+        row_plus |= 1;
+    }
+
+    *plus = row_minus | !(xv | row_plus);
+    *minus = row_plus & xv;
+    carry_out
+}
+
+/// The characters of `text` from `start` on, along with their ranges, an invalid byte being a
+/// character of its own (`None`).
+///
+/// Unlike `utf8_chunks`, which checks a whole run of valid UTF-8 before returning it, this decodes
+/// one character at a time, so whatever isn't iterated over is never looked at.
+fn chars(text: &[u8], mut start: usize) -> impl Iterator<Item = (Range<usize>, Option<char>)> + '_ {
+    iter::from_fn(move || {
+        let first = *text.get(start)?;
+        let len = match first {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            // A continuation byte can't start a character.
+            _ => 0,
+        };
+
+        let c = text
+            .get(start..start + len)
+            .and_then(|bytes| str::from_utf8(bytes).ok())
+            .and_then(|c| c.chars().next());
+        let len = if c.is_some() { len } else { 1 };
+        start += len;
+        Some((start - len..start, c))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The smallest distance between `pattern` and any part of `text`, filling in the whole
+    /// matrix of distances.
+    fn slow_distance(pattern: &str, text: &str) -> usize {
+        let text: Vec<_> = text.chars().collect();
+        let mut row = vec![0; text.len() + 1];
+        for (i, p) in pattern.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for j in 1..=text.len() {
+                let substituted = diagonal + usize::from(text[j - 1] != p);
+                diagonal = row[j];
+                row[j] = substituted.min(row[j] + 1).min(row[j - 1] + 1);
+            }
+        }
+
+        row.into_iter().min().unwrap()
+    }
+
+    #[test]
+    fn distances() {
+        let pattern = Pattern::new("hello", false);
+        assert_eq!(pattern.distance(b"say hello!", 2), Some(0));
+        assert_eq!(pattern.distance(b"say helo!", 2), Some(1));
+        assert_eq!(pattern.distance(b"say jelo!", 2), Some(2));
+        assert_eq!(pattern.distance(b"goodbye", 2), None);
+        assert!(pattern.is_match(b"hallo", 1));
+        assert!(!pattern.is_match(b"hxllx", 1));
+
+        // Anything is within the pattern's length of it.
+        assert_eq!(pattern.distance(b"", 5), Some(5));
+        assert_eq!(Pattern::new("", false).distance(b"abc", 0), Some(0));
+    }
+
+    #[test]
+    fn agrees_with_the_slow_way() {
+        // A pattern longer than a block has to carry between blocks.
+        let long = "the quick brown fox jumps over the lazy dog, ".repeat(3);
+        let texts = [
+            "the quick brown fox",
+            "teh quikc borwn fxo jumsp",
+            "a lazy dog jumps over the quick brown fox",
+            &long.replace('o', "0"),
+            &long.replace(' ', ""),
+            "",
+        ];
+
+        for pattern in [
+            "fox",
+            "quick brwn",
+            "ÿ",
+            long.as_str(),
+            &long[..64],
+            &long[..65],
+        ] {
+            let prepared = Pattern::new(pattern, false);
+            for text in texts {
+                let expected = slow_distance(pattern, text);
+                assert_eq!(
+                    prepared.distance(text.as_bytes(), usize::MAX),
+                    Some(expected),
+                    "{pattern:?} in {text:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn finds_whole_matches() {
+        let pattern = Pattern::new("hello", false);
+        let text = "hallo, jello and hello";
+        let (range, distance) = pattern.find(text.as_bytes(), 0, 1).unwrap();
+        assert_eq!((&text[range.clone()], distance), ("hallo", 1));
+
+        let (range, distance) = pattern.find(text.as_bytes(), range.end, 1).unwrap();
+        assert_eq!((&text[range.clone()], distance), ("jello", 1));
+
+        let (range, distance) = pattern.find(text.as_bytes(), range.end, 1).unwrap();
+        assert_eq!((&text[range.clone()], distance), ("hello", 0));
+        assert_eq!(pattern.find(text.as_bytes(), range.end, 1), None);
+
+        // "hel" is already close enough, but there's better right after it.
+        let (range, distance) = pattern.find(b"hello", 0, 2).unwrap();
+        assert_eq!((range, distance), (0..5, 0));
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        let pattern = Pattern::new("señor", false);
+        let text = "el senor y la señora";
+        let (range, distance) = pattern.find(text.as_bytes(), 0, 1).unwrap();
+        assert_eq!((&text[range.clone()], distance), ("senor", 1));
+
+        let (range, distance) = pattern.find(text.as_bytes(), range.end, 1).unwrap();
+        assert_eq!((&text[range], distance), ("señor", 0));
+
+        // An invalid byte is a character that's never right.
+        assert_eq!(pattern.distance(b"se\xffor", 2), Some(1));
+    }
+
+    #[test]
+    fn decodes_like_utf8_chunks() {
+        let text = "aé€😀"
+            .as_bytes()
+            .iter()
+            .chain(b"\xe2\x82a\xff\x80\xf0\x9f\x98");
+        let text: Vec<u8> = text.copied().collect();
+
+        let mut expected = Vec::new();
+        let mut start = 0;
+        for chunk in text.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                expected.push((start..start + c.len_utf8(), Some(c)));
+                start += c.len_utf8();
+            }
+
+            for _ in chunk.invalid() {
+                expected.push((start..start + 1, None));
+                start += 1;
+            }
+        }
+
+        assert_eq!(chars(&text, 0).collect::<Vec<_>>(), expected);
+        assert_eq!(chars(&text, 3).next(), Some((3..6, Some('€'))));
+    }
+
+    #[test]
+    fn ignores_case_a_character_at_a_time() {
+        let pattern = Pattern::new("STRASSE", true);
+        let text = "Straße, STRASE";
+        let (range, distance) = pattern.find(text.as_bytes(), 0, 1).unwrap();
+        assert_eq!((&text[range.clone()], distance), ("Straße", 0));
+
+        let (range, distance) = pattern.find(text.as_bytes(), range.end, 1).unwrap();
+        assert_eq!((&text[range], distance), ("STRASE", 1));
+
+        // A match that only takes part of what a character folds to takes all of it.
+        let (range, distance) = Pattern::new("as", true)
+            .find("aß".as_bytes(), 0, 0)
+            .unwrap();
+        assert_eq!((range, distance), (0..3, 0));
+    }
+}
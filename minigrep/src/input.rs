@@ -0,0 +1,140 @@
+//! Working out what to search from the paths in the config, and searching each of those.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Write},
+    iter,
+    path::PathBuf,
+};
+
+use crate::{decompress, edit, printer::Printer, walk, BinaryFiles, Config, Matcher, SortBy};
+
+/// What stdin is called in the output, like grep does.
+const STDIN_NAME: &str = "(standard input)";
+
+/// Something to search.
+pub enum Input {
+    Stdin,
+    /// A file given by its path.
+    File(PathBuf),
+    /// A file found while searching a directory, which is skipped if it looks binary (unless the
+    /// config says what to do with binary files).
+    TreeFile(PathBuf),
+}
+
+/// Returns everything `config` asks to search, in order, walking directories as it goes.
+///
+/// Problems found while walking (like a directory we can't read) are returned in between, as the
+/// message to report.
+pub fn inputs(config: &Config) -> impl Iterator<Item = Result<Input, String>> + '_ {
+    config.paths.iter().flat_map(|path| {
+        let inputs: Box<dyn Iterator<Item = _>> = if path.as_os_str() == "-" {
+            Box::new(iter::once(Ok(Input::Stdin)))
+        } else if config.recursive {
+            let files = walk::files(
+                path,
+                &config.include,
+                &config.exclude,
+                config.sort_by == Some(SortBy::Path),
+            );
+
+            match files {
+                Ok(files) => Box::new(files.map(|file| match file {
+                    Ok(file) => Ok(Input::TreeFile(file)),
+                    Err(e) => Err(e.to_string()),
+                })),
+                Err(e) => Box::new(iter::once(Err(format!("{}: {e}", path.display())))),
+            }
+        } else {
+            Box::new(iter::once(Ok(Input::File(path.clone()))))
+        };
+
+        inputs
+    })
+}
+
+impl Input {
+    /// What this input is called in the output.
+    pub fn name(&self) -> String {
+        match self {
+            Input::Stdin => STDIN_NAME.to_string(),
+            Input::File(path) | Input::TreeFile(path) => path.display().to_string(),
+        }
+    }
+
+    /// Searches this input, printing the results with `printer`, or makes the replacements in it
+    /// with `--in-place`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the input can't be read (or written to, with `--in-place`), or if writing the
+    /// output fails.
+    pub fn search<W: Write>(
+        &self,
+        matcher: &dyn Matcher,
+        config: &Config,
+        printer: &mut Printer<'_, W>,
+    ) -> io::Result<()> {
+        if config.in_place {
+            return self.edit(matcher, config, printer);
+        }
+
+        let mut reader: Box<dyn BufRead> = match self {
+            Input::Stdin => Box::new(io::stdin().lock()),
+            Input::File(path) | Input::TreeFile(path) => {
+                Box::new(BufReader::new(File::open(path)?))
+            }
+        };
+
+        // Matches are still reported with the compressed file's name.
+        if config.search_zip {
+            reader = decompress::decompress(reader)?;
+        }
+
+        // The first chunk of the input is enough to tell binary files apart, and it's already in
+        // the reader's buffer, so sniffing it doesn't read anything twice.
+        let binary = walk::is_binary(reader.fill_buf()?);
+        let binary_files = config.binary_files.unwrap_or(match self {
+            Input::TreeFile(_) => BinaryFiles::WithoutMatch,
+            _ => BinaryFiles::Binary,
+        });
+
+        match binary_files {
+            BinaryFiles::WithoutMatch if binary => Ok(()),
+            BinaryFiles::Text => printer.print_matches(matcher, &self.name(), reader, false),
+            _ => printer.print_matches(matcher, &self.name(), reader, binary),
+        }
+    }
+
+    /// Replaces the matches in this input's file as `config` says, or prints a diff of what would
+    /// change with `--dry-run`.
+    fn edit<W: Write>(
+        &self,
+        matcher: &dyn Matcher,
+        config: &Config,
+        printer: &mut Printer<'_, W>,
+    ) -> io::Result<()> {
+        let path = match self {
+            Input::Stdin => return Err(io::Error::other("can't edit standard input in place")),
+            Input::File(path) | Input::TreeFile(path) => path,
+        };
+
+        // Unlike searching, editing needs the whole file at once.
+        let contents = fs::read(path)?;
+
+        // Replacing text in a binary file would most likely break it.
+        if walk::is_binary(&contents) && config.binary_files != Some(BinaryFiles::Text) {
+            return Ok(());
+        }
+
+        let replacement = config.replace.as_deref().unwrap_or_default();
+        let changes = edit::changes(matcher, &contents, replacement);
+        if changes.is_empty() {
+            Ok(())
+        } else if config.dry_run {
+            printer.print_diff(&self.name(), &contents, &changes)
+        } else {
+            edit::write_in_place(path, &edit::apply(&contents, &changes))
+        }
+    }
+}
@@ -1,88 +1,154 @@
-use std::{env, error::Error, fs};
+use std::{
+    error::Error,
+    io::{self, IsTerminal},
+};
 
-#[derive(Debug)]
-pub struct Config {
-    pub query: String,
-    pub file_path: String,
-    pub ignore_case: bool,
-}
-
-const INVALID_USAGE: &str = "Incorrect usage! Must specify two arguments: <query> <file_path>";
-
-impl Config {
-    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
-        // If we wanted to assign args[1] to the `query` variable and args[2] to the `file_path variable`,
-        // we'd have to use `.clone()`, as we can't take ownership of the String away from the args array:
-        // (`args` used to be a `&[String]`)
-        // let query = args[1].clone();
-        // let file_path = args[2].clone();
+mod config;
+mod context;
+mod decompress;
+mod edit;
+mod fold;
+mod fuzzy;
+mod input;
+mod matcher;
+mod parallel;
+mod printer;
+mod stream;
+mod walk;
 
-        // However, we can instead use an iterator that returns ownership of these strings!
-        let query = args.nth(1).ok_or(INVALID_USAGE)?;
-        let file_path = args.next().ok_or(INVALID_USAGE)?;
+pub use config::{BinaryFiles, ColorChoice, Config, ConfigError, SortBy};
+pub use matcher::{
+    find_iter, replace_all, CaseInsensitiveMatcher, FindIter, FuzzyMatcher, LiteralMatcher,
+    LiteralSetMatcher, Matcher, RegexMatcher, WholeLineMatcher, WholeWordMatcher,
+};
+pub use stream::{search_reader, LineMatch, SearchReader};
 
-        let ignore_case = env::var("IGNORE_CASE").is_ok();
-
-        return Ok(Config {
-            query,
-            file_path,
-            ignore_case,
-        });
-    }
-}
+use context::ContextWindow;
+use printer::Printer;
 
 // `Box<dyn Error>`: "a boxed type that implements the Error trait"
 pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.file_path)?;
+    let matcher = config.matcher()?;
+    let stdout = io::stdout();
+    let color = match config.color {
+        ColorChoice::Auto => stdout.is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
 
-    let result = if config.ignore_case {
-        search_case_insensitive(&config.query, &contents)
+    let mut printer = Printer::new(&config, stdout.lock(), color);
+    let result = if config.threads > 1 {
+        parallel::search_all(
+            &config,
+            matcher.as_ref(),
+            &mut printer,
+            color,
+            config.threads,
+        )
     } else {
-        search(&config.query, &contents)
+        search_all(&config, matcher.as_ref(), &mut printer)
     };
 
-    for line in result {
-        println!("{line}");
-    }
+    let failed = match result.and_then(|failed| printer.into_inner().map(|_| failed)) {
+        Ok(failed) => failed,
+        // Whoever was reading the output (like `head`) doesn't want any more of it.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
 
-    return Ok(());
+    match failed {
+        0 => Ok(()),
+        1 => Err("1 file couldn't be searched".into()),
+        n => Err(format!("{n} files couldn't be searched").into()),
+    }
 }
 
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    return contents.lines().filter(|line| line.contains(query)).collect();
-
-    /*let mut result = Vec::new();
+/// Searches everything `config` asks for, one input after the other, printing the results with
+/// `printer`. Returns how many inputs couldn't be searched.
+///
+/// A file we can't read shouldn't stop us from searching the rest, so errors are reported as they
+/// happen, and only make the whole run fail at the end.
+fn search_all<W: io::Write>(
+    config: &Config,
+    matcher: &dyn Matcher,
+    printer: &mut Printer<'_, W>,
+) -> io::Result<usize> {
+    let mut failed = 0;
+    for input in input::inputs(config) {
+        let message = match input {
+            Err(message) => message,
+            Ok(input) => match input.search(matcher, config, printer) {
+                Ok(()) => continue,
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Err(e),
+                Err(e) => format!("{}: {e}", input.name()),
+            },
+        };
 
-    for line in contents.lines() {
-        if line.contains(query) {
-            result.push(line);
-        }
+        eprintln!("minigrep: {message}");
+        failed += 1;
     }
 
-    return result;*/
+    Ok(failed)
 }
 
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+/// Returns every line of `contents` in which `matcher` finds a match.
+///
+/// This needs the whole text in memory, see `search_reader` for searching as it's being read.
+pub fn search_with<'a>(matcher: &dyn Matcher, contents: &'a str) -> Vec<&'a str> {
+    // Note: This used to be `contents.lines().filter(|line| matcher.is_match(line)).collect()`,
+    // which checks every line. Searching the whole text for candidates first is much faster when
+    // only a few lines match, since most of it gets skipped without ever being split into lines.
     let mut result = Vec::new();
-    let query = query.to_ascii_lowercase();
+    let mut start = 0;
+    while let Some((line, next)) = stream::next_matching_line(matcher, contents.as_bytes(), start) {
+        result.push(&contents[line]);
+        start = next;
+    }
 
-    let mut line_lowercase = String::new();
-    for line in contents.lines() {
-        line_lowercase.push_str(line);
-        line_lowercase.make_ascii_lowercase();
+    result
+}
 
-        if line_lowercase.contains(&query) {
-            result.push(line);
-        }
+/// Like `search_with`, but also takes up to `before` lines before and `after` lines after each
+/// matching line. Lines that are next to each other are returned in the same group, so the
+/// groups are what grep would print with `--` between them.
+pub fn search_context<'a>(
+    matcher: &dyn Matcher,
+    contents: &'a str,
+    before: usize,
+    after: usize,
+) -> Vec<Vec<&'a str>> {
+    let mut groups: Vec<Vec<&str>> = Vec::new();
+    let mut window = ContextWindow::new(before, after);
+    let mut last_index = None;
+    for (index, line) in contents.lines().enumerate() {
+        let selected = matcher.is_match(line.as_bytes());
+        window.push((index, line), selected, |(index, line), _| {
+            match groups.last_mut() {
+                Some(group) if last_index == Some(index - 1) => group.push(line),
+                _ => groups.push(vec![line]),
+            }
 
-        line_lowercase.clear();
+            last_index = Some(index);
+        });
     }
 
-    return result;
+    groups
+}
+
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    search_with(&LiteralMatcher::new(query), contents)
+}
+
+/// Like `search`, but ignoring case. Case is compared with Unicode case folding, so this works
+/// for non-ASCII text too.
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    search_with(&CaseInsensitiveMatcher::new(query), contents)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{ffi::OsString, path::PathBuf};
+
     use super::*;
 
     #[test]
@@ -108,7 +174,147 @@ Trust me.";
 
         assert_eq!(
             vec!["Rust:", "Trust me."],
-            search_case_insensitive(&query, &contents)
+            search_case_insensitive(query, contents)
         )
     }
+
+    #[test]
+    fn case_insensitive_non_ascii() {
+        let contents = "\
+Hauptstraße 5
+HAUPTSTRASSE 7
+ΟΔΥΣΣΕΥΣ
+Одиссея";
+
+        assert_eq!(
+            vec!["Hauptstraße 5", "HAUPTSTRASSE 7"],
+            search_case_insensitive("hauptstrasse", contents)
+        );
+        assert_eq!(
+            vec!["ΟΔΥΣΣΕΥΣ"],
+            search_case_insensitive("Οδυσσευς", contents)
+        );
+        assert_eq!(
+            vec!["Одиссея"],
+            search_case_insensitive("ОДИССЕЯ", contents)
+        );
+    }
+
+    #[test]
+    fn regex_search() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+        let matcher = RegexMatcher::new(r"^\w+:$|thr[aeiou]e", false).unwrap();
+        assert_eq!(
+            vec!["Rust:", "Pick three."],
+            search_with(&matcher, contents)
+        );
+
+        let matcher = RegexMatcher::new("^t", true).unwrap();
+        assert_eq!(vec!["Trust me."], search_with(&matcher, contents));
+    }
+
+    #[test]
+    fn regex_candidates_are_checked_line_by_line() {
+        let contents = "a\r\nb \nab\r\nend";
+
+        // `$` has to match before `\r\n` and at the very end.
+        let matcher = RegexMatcher::new("[ad]$", false).unwrap();
+        assert_eq!(vec!["a", "end"], search_with(&matcher, contents));
+
+        // A match can start on one line and end on the next, but only lines that match on their
+        // own are returned.
+        let matcher = RegexMatcher::new(r"a\s*b", false).unwrap();
+        assert_eq!(vec!["ab"], search_with(&matcher, contents));
+
+        // `\A` is the start of each line, not of the whole text.
+        let matcher = RegexMatcher::new(r"\Ab", false).unwrap();
+        assert_eq!(vec!["b "], search_with(&matcher, contents));
+
+        let matcher = WholeWordMatcher::new(Box::new(LiteralMatcher::new("b")));
+        assert_eq!(vec!["b "], search_with(&matcher, contents));
+
+        // The same goes for `^` with multi-line mode turned off, even with other flags set with it.
+        let contents = "bar\nfoo\nFOO bar";
+        for pattern in ["(?-m)^foo", "(?i-m)^foo", "(?s-m:^f)oo", "(?m)(?i-m:^foo)"] {
+            let matcher = RegexMatcher::new(pattern, false).unwrap();
+            let expected = if pattern.contains('i') {
+                vec!["foo", "FOO bar"]
+            } else {
+                vec!["foo"]
+            };
+            assert_eq!(expected, search_with(&matcher, contents), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn context_groups() {
+        let contents = "\
+I'm nobody! Who are you?
+Are you nobody, too?
+Then there's a pair of us - don't tell!
+They'd banish us, you know.
+
+How dreary to be somebody!
+How public, like a frog
+To tell your name the livelong day
+To an admiring bog!";
+
+        let matcher = LiteralMatcher::new("body");
+        assert_eq!(
+            search_context(&matcher, contents, 0, 1),
+            vec![
+                vec![
+                    "I'm nobody! Who are you?",
+                    "Are you nobody, too?",
+                    "Then there's a pair of us - don't tell!"
+                ],
+                vec!["How dreary to be somebody!", "How public, like a frog"],
+            ]
+        );
+
+        let matcher = LiteralMatcher::new("tell");
+        assert_eq!(
+            search_context(&matcher, contents, 1, 0),
+            vec![
+                vec![
+                    "Are you nobody, too?",
+                    "Then there's a pair of us - don't tell!"
+                ],
+                vec![
+                    "How public, like a frog",
+                    "To tell your name the livelong day"
+                ],
+            ]
+        );
+
+        // With enough context, everything ends up in a single group.
+        assert_eq!(search_context(&matcher, contents, 2, 2).len(), 1);
+        assert_eq!(
+            search_context(&matcher, contents, 0, 0),
+            search_with(&matcher, contents)
+                .into_iter()
+                .map(|line| vec![line])
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn build_reads_regex_flag() {
+        let args = ["minigrep", "-E", "a|b", "poem.txt"].map(OsString::from);
+        let config = Config::build(args.into_iter()).unwrap();
+        assert!(config.regex);
+        assert_eq!(config.patterns, vec!["a|b"]);
+        assert_eq!(config.paths, vec![PathBuf::from("poem.txt")]);
+
+        let args = ["minigrep", "a|b", "poem.txt"].map(OsString::from);
+        assert!(!Config::build(args.into_iter()).unwrap().regex);
+
+        let args = ["minigrep", "--regex"].map(OsString::from);
+        assert!(Config::build(args.into_iter()).is_err());
+    }
 }
@@ -1,14 +1,21 @@
 use std::{env, process};
 
-use minigrep::Config;
+use minigrep::{Config, ConfigError};
 
 fn main() {
-    // Note: `env::args()` panics if any of the arguments contains invalid unicode. If you need
-    // to accept args with invalid unicode use `std::env::args_os` instead.
-    let args = env::args();
+    // Note: `env::args()` panics if any of the arguments contains invalid unicode, which paths
+    // can. `env::args_os()` returns `OsString`s instead, and `Config::build` only complains if one
+    // that has to be text (like the query) isn't valid unicode.
+    let args = env::args_os();
     let config_result = Config::build(args);
 
     let config = config_result.unwrap_or_else(|err| {
+        // Asking for the help or the version isn't a failure, so those go to stdout.
+        if let ConfigError::Help | ConfigError::Version = err {
+            println!("{err}");
+            process::exit(0);
+        }
+
         // Note the usage of `eprintln!` instead of `println!` to print to stderr
         eprintln!("{}", err);
         process::exit(1);
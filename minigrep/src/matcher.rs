@@ -0,0 +1,629 @@
+use std::ops::Range;
+
+use aho_corasick::{AhoCorasick, BuildError, Input, MatchKind};
+use memchr::memmem::Finder;
+use regex::bytes::{Regex, RegexBuilder};
+use regex_syntax::ParserBuilder;
+
+use crate::{
+    fold::{self, Folded},
+    fuzzy,
+};
+
+/// Something that can find occurrences of a pattern within a line of text.
+///
+/// Every search mode (literal, case-insensitive, regex...) implements this, so they all share the
+/// same search code. Matchers need to be `Send + Sync` so several threads can search with the same
+/// one.
+///
+/// Text is searched as bytes, since files don't have to be valid UTF-8. Whatever is valid UTF-8 is
+/// matched as text, the invalid bytes in between just never match anything but themselves.
+pub trait Matcher: Send + Sync {
+    /// Returns the byte range of the first match in `line` that starts at or after `start`.
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>>;
+
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.find_at(line, 0).is_some()
+    }
+
+    /// Looks for the next line worth checking in `text`, which can hold many lines, starting from
+    /// `start`. Returns a position in (or at the end of) that line, or `None` if no line from
+    /// `start` on can match.
+    ///
+    /// This lets matchers skip over big chunks of text at once instead of checking it line by
+    /// line, which is much faster when matches are rare. The line found still has to be checked
+    /// with `is_match`, it's only a candidate. The default doesn't skip anything, and returns
+    /// `start` so every line gets checked.
+    fn find_candidate(&self, text: &[u8], start: usize) -> Option<usize> {
+        let _ = text;
+        Some(start)
+    }
+
+    /// How many edits `line` is away from matching exactly, or `None` if it doesn't match at all.
+    /// Only approximate matchers (like `FuzzyMatcher`) ever say anything but 0.
+    fn distance(&self, line: &[u8]) -> Option<usize> {
+        self.is_match(line).then_some(0)
+    }
+
+    /// Appends what the match at `range` in `line` (as found by `find_at`) should be replaced
+    /// with to `dst`. By default that's `replacement` as it is, but regexes expand the groups it
+    /// refers to, like `$1`.
+    fn replace_match(
+        &self,
+        line: &[u8],
+        range: Range<usize>,
+        replacement: &str,
+        dst: &mut Vec<u8>,
+    ) {
+        let _ = (line, range);
+        dst.extend_from_slice(replacement.as_bytes());
+    }
+}
+
+/// Returns an iterator over every match of `matcher` in `line`, from left to right, without
+/// overlaps.
+pub fn find_iter<'a>(matcher: &'a dyn Matcher, line: &'a [u8]) -> FindIter<'a> {
+    FindIter {
+        matcher,
+        line,
+        next_start: Some(0),
+    }
+}
+
+/// The iterator returned by `find_iter`.
+pub struct FindIter<'a> {
+    matcher: &'a dyn Matcher,
+    line: &'a [u8],
+    /// Where to search for the next match, or `None` once the end of the line was searched.
+    next_start: Option<usize>,
+}
+
+impl Iterator for FindIter<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        let range = self.matcher.find_at(self.line, self.next_start?);
+        self.next_start = match &range {
+            // An empty match would be found again at the same spot, so skip ahead a character.
+            Some(range) if range.is_empty() => {
+                char_len_at(self.line, range.end).map(|len| range.end + len)
+            }
+            Some(range) => Some(range.end),
+            None => None,
+        };
+
+        range
+    }
+}
+
+/// Returns `line` with every match of `matcher` in it replaced by `replacement` (see
+/// `Matcher::replace_match`).
+pub fn replace_all(matcher: &dyn Matcher, line: &[u8], replacement: &str) -> Vec<u8> {
+    let mut replaced = Vec::with_capacity(line.len());
+    let mut copied = 0;
+    for range in find_iter(matcher, line) {
+        replaced.extend_from_slice(&line[copied..range.start]);
+        copied = range.end;
+        matcher.replace_match(line, range, replacement, &mut replaced);
+    }
+
+    replaced.extend_from_slice(&line[copied..]);
+    replaced
+}
+
+/// The length of the character starting at `index` in `text`, counting an invalid byte as a
+/// character of its own. `None` at the end of the text.
+fn char_len_at(text: &[u8], index: usize) -> Option<usize> {
+    let chunk = text[index..].utf8_chunks().next()?;
+    Some(chunk.valid().chars().next().map_or(1, char::len_utf8))
+}
+
+/// The character ending right before `index` in `text`, if it's valid UTF-8.
+fn char_before(text: &[u8], index: usize) -> Option<char> {
+    // A character is at most 4 bytes long, so one of these has to be it.
+    (1..=index.min(4)).find_map(|len| {
+        let c = std::str::from_utf8(&text[index - len..index]).ok()?;
+        c.chars().next_back()
+    })
+}
+
+/// The character starting at `index` in `text`, if it's valid UTF-8.
+fn char_after(text: &[u8], index: usize) -> Option<char> {
+    text[index..].utf8_chunks().next()?.valid().chars().next()
+}
+
+/// Matches an exact string.
+pub struct LiteralMatcher {
+    // `memmem` picks the fastest substring search for the needle (SIMD-accelerated where
+    // available, falling back to Two-Way), and only has to analyze the needle once.
+    finder: Finder<'static>,
+}
+
+impl LiteralMatcher {
+    pub fn new(needle: &str) -> LiteralMatcher {
+        LiteralMatcher {
+            finder: Finder::new(needle).into_owned(),
+        }
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        let index = self.finder.find(&line[start..])? + start;
+        Some(index..index + self.finder.needle().len())
+    }
+
+    fn find_candidate(&self, text: &[u8], start: usize) -> Option<usize> {
+        // Any line with the needle in it matches, so there's nothing to do but find it.
+        Some(self.finder.find(&text[start..])? + start)
+    }
+}
+
+/// Matches a string ignoring case, using Unicode case folding (see the `fold` module), so for
+/// example "STRASSE" matches "straße" and "ПРИВЕТ" matches "привет".
+pub struct CaseInsensitiveMatcher {
+    needle: String,
+}
+
+impl CaseInsensitiveMatcher {
+    pub fn new(needle: &str) -> CaseInsensitiveMatcher {
+        CaseInsensitiveMatcher {
+            needle: fold::fold(needle),
+        }
+    }
+}
+
+impl Matcher for CaseInsensitiveMatcher {
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        // Folding can change the length of the text (`ß` becomes `ss`), so the matches found in
+        // the folded copy have to be mapped back to the original line.
+        Folded::new(&line[start..], start).find(&self.needle)
+    }
+}
+
+/// Matches any of several strings at once, optionally ignoring case like
+/// `CaseInsensitiveMatcher` does.
+///
+/// This uses the Aho-Corasick algorithm, which finds all of the strings in a single pass over the
+/// text, so searching for hundreds of them isn't much slower than searching for one. If several
+/// match at the same spot, the longest one is picked.
+pub struct LiteralSetMatcher {
+    searcher: AhoCorasick,
+    ignore_case: bool,
+}
+
+impl LiteralSetMatcher {
+    /// # Errors
+    ///
+    /// Fails if there are so many needles (or they're so long) that the searcher would be too
+    /// big.
+    pub fn new(needles: &[String], ignore_case: bool) -> Result<LiteralSetMatcher, BuildError> {
+        let mut builder = AhoCorasick::builder();
+        builder.match_kind(MatchKind::LeftmostLongest);
+        let searcher = if ignore_case {
+            builder.build(needles.iter().map(|needle| fold::fold(needle)))?
+        } else {
+            builder.build(needles)?
+        };
+
+        Ok(LiteralSetMatcher {
+            searcher,
+            ignore_case,
+        })
+    }
+
+    fn find_in(&self, text: &[u8], start: usize) -> Option<Range<usize>> {
+        let input = Input::new(text).span(start..text.len());
+        self.searcher.find(input).map(|m| m.range())
+    }
+}
+
+impl Matcher for LiteralSetMatcher {
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        if self.ignore_case {
+            Folded::new(&line[start..], start).find_with(|text, from| self.find_in(text, from))
+        } else {
+            self.find_in(line, start)
+        }
+    }
+
+    fn find_candidate(&self, text: &[u8], start: usize) -> Option<usize> {
+        // Folding the whole text just to look for candidates would cost more than it saves.
+        if self.ignore_case {
+            Some(start)
+        } else {
+            self.find_in(text, start).map(|range| range.start)
+        }
+    }
+}
+
+/// Matches anything within a number of edits (inserted, deleted or substituted characters) of a
+/// string, optionally ignoring case like `CaseInsensitiveMatcher` does. See the `fuzzy` module
+/// for how.
+pub struct FuzzyMatcher {
+    pattern: fuzzy::Pattern,
+    max_distance: usize,
+}
+
+impl FuzzyMatcher {
+    pub fn new(needle: &str, max_distance: usize, ignore_case: bool) -> FuzzyMatcher {
+        FuzzyMatcher {
+            // Unlike the other matchers, this folds the line a character at a time as it goes,
+            // rather than all of it up front, so that finding each match only looks at as much of
+            // the line as it has to.
+            pattern: fuzzy::Pattern::new(needle, ignore_case),
+            max_distance,
+        }
+    }
+}
+
+impl Matcher for FuzzyMatcher {
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        let (range, _) = self.pattern.find(line, start, self.max_distance)?;
+        Some(range)
+    }
+
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.pattern.is_match(line, self.max_distance)
+    }
+
+    fn distance(&self, line: &[u8]) -> Option<usize> {
+        self.pattern.distance(line, self.max_distance)
+    }
+}
+
+/// Matches a regular expression, with the syntax of the `regex` crate.
+pub struct RegexMatcher {
+    regex: Regex,
+    /// The same regex, but with `^` and `$` matching at the start and end of every line, for
+    /// finding candidates in text with many lines. `None` if the pattern has anchors that can't
+    /// be made to work like that.
+    multi_line: Option<Regex>,
+}
+
+impl RegexMatcher {
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<RegexMatcher, regex::Error> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()?;
+
+        // `\A` and `\z` always mean the start and end of the whole text, and so do `^` and `$`
+        // with `(?-m)` (which can be hidden in groups like `(?i-m:...)`), so if any of those are
+        // left in the parsed pattern, it's only ever run line by line.
+        let multi_line = if anchors_whole_text(pattern) {
+            None
+        } else {
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .multi_line(true)
+                // Lines can end with `\r\n` too, and `$` has to match before the `\r` in those.
+                .crlf(true)
+                .build()?;
+            Some(regex)
+        };
+
+        Ok(RegexMatcher { regex, multi_line })
+    }
+}
+
+/// Whether `pattern`, parsed in multi-line mode, still has assertions for the start or end of the
+/// whole text. Patterns that can't be parsed are assumed to.
+fn anchors_whole_text(pattern: &str) -> bool {
+    let hir = ParserBuilder::new()
+        .multi_line(true)
+        .crlf(true)
+        // Like `regex::bytes`, which can match invalid UTF-8.
+        .utf8(false)
+        .build()
+        .parse(pattern);
+
+    hir.map_or(true, |hir| {
+        hir.properties().look_set().contains_anchor_haystack()
+    })
+}
+
+impl Matcher for RegexMatcher {
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        self.regex.find_at(line, start).map(|m| m.range())
+    }
+
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.regex.is_match(line)
+    }
+
+    fn find_candidate(&self, text: &[u8], start: usize) -> Option<usize> {
+        // A match of the multi-line regex can span several lines (for example with `\s` or
+        // `[^a]`, which match line breaks), so it only says that the line it starts in is worth
+        // checking.
+        match &self.multi_line {
+            Some(regex) => regex.find_at(text, start).map(|m| m.start()),
+            None => Some(start),
+        }
+    }
+
+    fn replace_match(
+        &self,
+        line: &[u8],
+        range: Range<usize>,
+        replacement: &str,
+        dst: &mut Vec<u8>,
+    ) {
+        // Searching again from the start of the match finds the same match, this time with its
+        // groups.
+        match self.regex.captures_at(line, range.start) {
+            Some(captures) => captures.expand(replacement.as_bytes(), dst),
+            None => dst.extend_from_slice(replacement.as_bytes()),
+        }
+    }
+}
+
+/// Only accepts the matches of another matcher that aren't part of a bigger word, that is, the
+/// ones not touching a letter, digit or underscore on either side.
+///
+/// This is only meant for literal patterns: a regex like `ab|abc` could have a longer match than
+/// the one found, so regexes get the word boundaries built in instead.
+pub struct WholeWordMatcher {
+    inner: Box<dyn Matcher>,
+}
+
+impl WholeWordMatcher {
+    pub fn new(inner: Box<dyn Matcher>) -> WholeWordMatcher {
+        WholeWordMatcher { inner }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Matcher for WholeWordMatcher {
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        let mut start = start;
+        loop {
+            let range = self.inner.find_at(line, start)?;
+            let before = char_before(line, range.start);
+            let after = char_after(line, range.end);
+            if !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char) {
+                return Some(range);
+            }
+
+            // Try again from the next character, so that in "cats cat" we skip over "cat" in
+            // "cats" but still find the second one.
+            start = range.start + char_len_at(line, range.start)?;
+        }
+    }
+
+    fn find_candidate(&self, text: &[u8], start: usize) -> Option<usize> {
+        self.inner.find_candidate(text, start)
+    }
+
+    fn replace_match(
+        &self,
+        line: &[u8],
+        range: Range<usize>,
+        replacement: &str,
+        dst: &mut Vec<u8>,
+    ) {
+        self.inner.replace_match(line, range, replacement, dst);
+    }
+}
+
+/// Only accepts the matches of another matcher that span the whole line.
+pub struct WholeLineMatcher {
+    inner: Box<dyn Matcher>,
+}
+
+impl WholeLineMatcher {
+    pub fn new(inner: Box<dyn Matcher>) -> WholeLineMatcher {
+        WholeLineMatcher { inner }
+    }
+}
+
+impl Matcher for WholeLineMatcher {
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        if start != 0 {
+            return None;
+        }
+
+        // Whatever matches the whole line has to start at 0, so it's the leftmost match.
+        self.inner
+            .find_at(line, 0)
+            .filter(|range| *range == (0..line.len()))
+    }
+
+    fn find_candidate(&self, text: &[u8], start: usize) -> Option<usize> {
+        self.inner.find_candidate(text, start)
+    }
+
+    fn replace_match(
+        &self,
+        line: &[u8],
+        range: Range<usize>,
+        replacement: &str,
+        dst: &mut Vec<u8>,
+    ) {
+        self.inner.replace_match(line, range, replacement, dst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_finds_every_occurrence() {
+        let matcher = LiteralMatcher::new("ab");
+        assert_eq!(matcher.find_at(b"xabxab", 0), Some(1..3));
+        assert_eq!(matcher.find_at(b"xabxab", 2), Some(4..6));
+        assert_eq!(matcher.find_at(b"xabxab", 5), None);
+    }
+
+    #[test]
+    fn case_insensitive_keeps_original_offsets() {
+        let matcher = CaseInsensitiveMatcher::new("RuSt");
+        assert_eq!(matcher.find_at(b"Trust me, RUST", 0), Some(1..5));
+        assert_eq!(matcher.find_at(b"Trust me, RUST", 2), Some(10..14));
+    }
+
+    #[test]
+    fn case_insensitive_handles_non_ascii_text() {
+        let matcher = CaseInsensitiveMatcher::new("STRASSE");
+        let line = "Die Straße, die STRASSE".as_bytes();
+        assert_eq!(matcher.find_at(line, 0), Some(4..11));
+        assert_eq!(matcher.find_at(line, 5), Some(17..24));
+
+        let matcher = CaseInsensitiveMatcher::new("ΣΟΦΙΑ");
+        assert!(matcher.is_match("η σοφια είναι".as_bytes()));
+        assert!(
+            !matcher.is_match("η σοφία είναι".as_bytes()),
+            "accents aren't case"
+        );
+
+        let matcher = CaseInsensitiveMatcher::new("привет");
+        assert_eq!(
+            matcher.find_at("Он сказал: ПРИВЕТ!".as_bytes(), 0),
+            Some(19..31)
+        );
+
+        let matcher = CaseInsensitiveMatcher::new("ὈΔΥΣΣΕΎΣ");
+        assert!(matcher.is_match("ὀδυσσεύς".as_bytes()));
+    }
+
+    #[test]
+    fn literal_set_finds_any_needle() {
+        let needles = ["cat", "category", "dog"].map(String::from);
+        let matcher = LiteralSetMatcher::new(&needles, false).unwrap();
+        assert_eq!(
+            find_iter(&matcher, b"dog, category, cat").collect::<Vec<_>>(),
+            vec![0..3, 5..13, 15..18]
+        );
+        assert!(!matcher.is_match(b"Dog"));
+        assert_eq!(matcher.find_candidate(b"a\nb\ndog", 0), Some(4));
+
+        let needles = ["STRASSE", "привет"].map(String::from);
+        let matcher = LiteralSetMatcher::new(&needles, true).unwrap();
+        let line = "ПРИВЕТ, Straße".as_bytes();
+        assert_eq!(
+            find_iter(&matcher, line).collect::<Vec<_>>(),
+            vec![0..12, 14..21]
+        );
+
+        assert!(!LiteralSetMatcher::new(&[], false)
+            .unwrap()
+            .is_match(b"anything"));
+    }
+
+    #[test]
+    fn regex_supports_anchors_classes_and_alternation() {
+        let matcher = RegexMatcher::new(r"^[A-Z]\w+:$", false).unwrap();
+        assert!(matcher.is_match(b"Rust:"));
+        assert!(!matcher.is_match(b"rust:"));
+        assert!(!matcher.is_match(b"Rust: safe"));
+
+        let matcher = RegexMatcher::new("fast|three", false).unwrap();
+        assert_eq!(matcher.find_at(b"safe, fast, productive.", 0), Some(6..10));
+        assert_eq!(matcher.find_at(b"Pick three.", 0), Some(5..10));
+
+        let matcher = RegexMatcher::new("RUST", true).unwrap();
+        assert!(matcher.is_match(b"Trust me."));
+    }
+
+    #[test]
+    fn find_iter_finds_every_match() {
+        let matcher = LiteralMatcher::new("aa");
+        assert_eq!(
+            find_iter(&matcher, b"aaaaa").collect::<Vec<_>>(),
+            vec![0..2, 2..4]
+        );
+
+        let matcher = RegexMatcher::new("x*", false).unwrap();
+        assert_eq!(
+            find_iter(&matcher, "axxé".as_bytes()).collect::<Vec<_>>(),
+            vec![0..0, 1..3, 3..3, 5..5]
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_only_matches_itself() {
+        let line = b"\xffcat\xfe caf\xc3";
+        let matcher = WholeWordMatcher::new(Box::new(LiteralMatcher::new("cat")));
+        assert_eq!(matcher.find_at(line, 0), Some(1..4));
+
+        let matcher = CaseInsensitiveMatcher::new("CAF");
+        assert_eq!(matcher.find_at(line, 0), Some(6..9));
+
+        let matcher = RegexMatcher::new("c.", false).unwrap();
+        assert_eq!(
+            find_iter(&matcher, line).collect::<Vec<_>>(),
+            vec![1..3, 6..8]
+        );
+        assert!(!RegexMatcher::new("[tf].", false).unwrap().is_match(line));
+
+        let matcher = RegexMatcher::new("", false).unwrap();
+        assert_eq!(find_iter(&matcher, b"\xffa").count(), 3);
+    }
+
+    #[test]
+    fn replaces_every_match() {
+        let matcher = LiteralMatcher::new("$1");
+        assert_eq!(replace_all(&matcher, b"a $1 b $1", "$2"), b"a $2 b $2");
+
+        let matcher = RegexMatcher::new(r"(\w+)@(?P<host>\w+)", false).unwrap();
+        assert_eq!(
+            replace_all(&matcher, b"me@home, you@work", "${host}:$1"),
+            b"home:me, work:you"
+        );
+
+        let matcher = WholeWordMatcher::new(Box::new(RegexMatcher::new("c(a)t", false).unwrap()));
+        assert_eq!(replace_all(&matcher, b"cats cat", "d${1}d"), b"cats dad");
+
+        let matcher = RegexMatcher::new("^", false).unwrap();
+        assert_eq!(replace_all(&matcher, b"quote", "> "), b"> quote");
+    }
+
+    #[test]
+    fn fuzzy_allows_a_few_edits() {
+        let matcher = FuzzyMatcher::new("rust", 1, false);
+        assert_eq!(
+            find_iter(&matcher, b"Rust: trust the rusty rost").collect::<Vec<_>>(),
+            vec![0..4, 7..11, 16..20, 22..26]
+        );
+        assert_eq!(matcher.distance(b"Rust"), Some(1));
+        assert_eq!(matcher.distance(b"rusty"), Some(0));
+        assert_eq!(matcher.distance(b"rats"), None);
+
+        let matcher = FuzzyMatcher::new("STRASSE", 1, true);
+        assert_eq!(matcher.find_at("die Straße".as_bytes(), 0), Some(4..11));
+        assert_eq!(matcher.distance("die Strase".as_bytes()), Some(1));
+
+        // Other matchers only ever match exactly.
+        assert_eq!(LiteralMatcher::new("rust").distance(b"trust"), Some(0));
+        assert_eq!(LiteralMatcher::new("rust").distance(b"rost"), None);
+    }
+
+    #[test]
+    fn invalid_regex_is_an_error() {
+        assert!(RegexMatcher::new("(unclosed", false).is_err());
+    }
+
+    #[test]
+    fn whole_word_skips_partial_words() {
+        let matcher = WholeWordMatcher::new(Box::new(LiteralMatcher::new("cat")));
+        assert_eq!(matcher.find_at(b"cats concat cat_ cat.", 0), Some(17..20));
+        assert_eq!(matcher.find_at(b"cat", 0), Some(0..3));
+        assert_eq!(matcher.find_at("ñcat cat".as_bytes(), 0), Some(6..9));
+        assert!(!matcher.is_match(b"scatter"));
+
+        let matcher = WholeWordMatcher::new(Box::new(LiteralMatcher::new("aa")));
+        assert_eq!(matcher.find_at(b"aaa aa", 0), Some(4..6));
+    }
+
+    #[test]
+    fn whole_line_needs_the_entire_line() {
+        let matcher = WholeLineMatcher::new(Box::new(CaseInsensitiveMatcher::new("pick three.")));
+        assert!(matcher.is_match(b"Pick three."));
+        assert!(!matcher.is_match(b"Pick three. Or four."));
+        assert!(!matcher.is_match(b" Pick three."));
+        assert_eq!(matcher.find_at(b"Pick three.", 1), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Searching several inputs at once on a pool of worker threads, for `-j`.
+//!
+//! One thread walks the inputs and hands them out to the workers through a channel they all share
+//! (like `webweb`'s `ThreadPool` does with its jobs). Each worker prints into a buffer of its own,
+//! and the calling thread writes the buffers out one at a time, so the output for a file never
+//! gets mixed up with another's.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    input::{self, Input},
+    printer::Printer,
+    Config, Matcher, SortBy,
+};
+
+/// What searching one input produced.
+struct Searched<'c> {
+    output: Printer<'c, Vec<u8>>,
+    /// The message to report if the input couldn't be searched.
+    error: Option<String>,
+}
+
+/// Searches everything `config` asks for using `threads` worker threads, writing the results to
+/// `printer`. Returns how many inputs couldn't be searched, which are reported as they come up.
+///
+/// The output of each input is written out as soon as it's ready, unless sorting, in which case
+/// they're written in order (which means holding on to the ones that finish early).
+///
+/// # Errors
+///
+/// Fails if writing to `printer` fails. Searching stops as soon as possible if it does.
+pub fn search_all<W: Write>(
+    config: &Config,
+    matcher: &dyn Matcher,
+    printer: &mut Printer<'_, W>,
+    color: bool,
+    threads: usize,
+) -> io::Result<usize> {
+    let (job_sender, job_receiver) = mpsc::sync_channel::<(usize, Input)>(threads * 4);
+    let job_receiver = Mutex::new(job_receiver);
+    let (result_sender, result_receiver) = mpsc::channel::<(usize, Searched)>();
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let results = result_sender.clone();
+        let stop = &stop;
+        scope.spawn(move || {
+            for (index, input) in input::inputs(config).enumerate() {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let sent = match input {
+                    Ok(input) => job_sender.send((index, input)).is_ok(),
+                    Err(message) => {
+                        let output = Printer::new(config, Vec::new(), color);
+                        let searched = Searched {
+                            output,
+                            error: Some(message),
+                        };
+                        results.send((index, searched)).is_ok()
+                    }
+                };
+
+                if !sent {
+                    break;
+                }
+            }
+        });
+
+        for _ in 0..threads {
+            let results = result_sender.clone();
+            let job_receiver = &job_receiver;
+            scope.spawn(move || loop {
+                let job = job_receiver.lock().unwrap().recv();
+                // Note: As in `webweb`'s `Worker`, the lock is released at the end of the `let`
+                // above, so the other workers can take jobs while this one is busy.
+
+                let Ok((index, input)) = job else {
+                    break;
+                };
+
+                // Once we know the output won't be written, drain the remaining jobs quickly.
+                if stop.load(Ordering::Relaxed) {
+                    continue;
+                }
+
+                let mut output = Printer::new(config, Vec::new(), color);
+                let error = input
+                    .search(matcher, config, &mut output)
+                    .err()
+                    .map(|e| format!("{}: {e}", input.name()));
+
+                // If the results aren't wanted any more, `stop` is already set, so keep draining
+                // the jobs: the producer could be waiting for room in the channel.
+                let _ = results.send((index, Searched { output, error }));
+            });
+        }
+
+        // Only the producer and workers hold senders now, so the loop below ends once they're
+        // all done.
+        drop(result_sender);
+
+        write_results(config, printer, result_receiver, stop)
+    })
+}
+
+/// Writes the results out as they come in, returning how many inputs couldn't be searched.
+///
+/// If writing fails, `stop` is set before `results` is dropped, so the workers know to stop
+/// searching before their results start failing to send.
+fn write_results<W: Write>(
+    config: &Config,
+    printer: &mut Printer<'_, W>,
+    results: mpsc::Receiver<(usize, Searched)>,
+    stop: &AtomicBool,
+) -> io::Result<usize> {
+    // Lines that are just as close keep the order of their inputs when sorting by score, so that
+    // needs the inputs in order too.
+    let ordered = matches!(config.sort_by, Some(SortBy::Path | SortBy::Score));
+    let mut failed = 0;
+    let mut write = |searched: Searched| {
+        if let Err(e) = printer.append(searched.output) {
+            stop.store(true, Ordering::Relaxed);
+            return Err(e);
+        }
+
+        if let Some(message) = searched.error {
+            eprintln!("minigrep: {message}");
+            failed += 1;
+        }
+
+        io::Result::Ok(())
+    };
+
+    let mut waiting = BTreeMap::new();
+    let mut next_index = 0;
+    for (index, searched) in results {
+        if !ordered {
+            write(searched)?;
+            continue;
+        }
+
+        waiting.insert(index, searched);
+        while let Some(searched) = waiting.remove(&next_index) {
+            write(searched)?;
+            next_index += 1;
+        }
+    }
+
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, ops::Range, sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::LiteralMatcher;
+
+    fn search(config: &Config, threads: usize) -> (String, usize) {
+        let matcher = LiteralMatcher::new("needle");
+        let mut printer = Printer::new(config, Vec::new(), false);
+        let failed = search_all(config, &matcher, &mut printer, false, threads).unwrap();
+        let output = String::from_utf8(printer.into_inner().unwrap()).unwrap();
+        (output, failed)
+    }
+
+    #[test]
+    fn matches_sequential_output_when_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..50 {
+            let contents = format!("hay\nneedle {i}\nhay\nneedle again {i}\n");
+            fs::write(dir.path().join(format!("file{i:02}.txt")), contents).unwrap();
+        }
+
+        let config = Config {
+            paths: vec![dir.path().to_path_buf()],
+            recursive: true,
+            line_number: true,
+            sort_by: Some(SortBy::Path),
+            ..Config::default()
+        };
+
+        let (expected, _) = search(&config, 1);
+        assert_eq!(expected.lines().count(), 100);
+        for threads in [2, 8] {
+            assert_eq!(search(&config, threads), (expected.clone(), 0));
+        }
+    }
+
+    #[test]
+    fn keeps_each_file_together() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..20 {
+            let contents = "needle\n".repeat(100);
+            fs::write(dir.path().join(format!("{i}.txt")), contents).unwrap();
+        }
+
+        let config = Config {
+            paths: vec![dir.path().to_path_buf()],
+            recursive: true,
+            ..Config::default()
+        };
+
+        let (output, _) = search(&config, 4);
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2000);
+        for file in lines.chunks(100) {
+            assert!(file.iter().all(|line| line == &file[0]));
+        }
+    }
+
+    /// Output that can't be written to, like a pipe whose reader went away. Remembers whether
+    /// anything tried to.
+    struct ClosedPipe(Arc<AtomicBool>);
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            self.0.store(true, Ordering::SeqCst);
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Matches the first line it's given right away, but only gets to the rest once the output
+    /// has failed, like workers that are still busy searching when that happens.
+    struct LateMatcher {
+        inner: LiteralMatcher,
+        started: AtomicBool,
+        output_failed: Arc<AtomicBool>,
+    }
+
+    impl Matcher for LateMatcher {
+        fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+            if self.started.swap(true, Ordering::SeqCst) {
+                while !self.output_failed.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                // Give the failure time to get to the other threads.
+                thread::sleep(Duration::from_millis(50));
+            }
+
+            self.inner.find_at(line, start)
+        }
+    }
+
+    #[test]
+    fn stops_when_the_output_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        // Many more files than fit in the job channel, so the producer has to wait for room.
+        let paths: Vec<_> = (0..200)
+            .map(|i| dir.path().join(format!("{i}.txt")))
+            .collect();
+        for path in &paths {
+            fs::write(path, "needle\n").unwrap();
+        }
+
+        let config = Config {
+            paths,
+            ..Config::default()
+        };
+
+        for threads in [1, 2, 8] {
+            let output_failed = Arc::new(AtomicBool::new(false));
+            let matcher = LateMatcher {
+                inner: LiteralMatcher::new("needle"),
+                started: AtomicBool::new(false),
+                output_failed: output_failed.clone(),
+            };
+            let mut printer = Printer::new(&config, ClosedPipe(output_failed), false);
+            let error = search_all(&config, &matcher, &mut printer, false, threads).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        }
+    }
+
+    #[test]
+    fn reports_missing_files_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let found = dir.path().join("found.txt");
+        fs::write(&found, "needle\n").unwrap();
+
+        let config = Config {
+            paths: vec![dir.path().join("missing.txt"), found.clone()],
+            sort_by: Some(SortBy::Path),
+            ..Config::default()
+        };
+
+        let (output, failed) = search(&config, 3);
+        assert_eq!(failed, 1);
+        assert_eq!(output, format!("{}:needle\n", found.display()));
+    }
+}
@@ -0,0 +1,623 @@
+//! Writing search results out, in whichever format the config asks for.
+
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    io::{self, BufRead, Write},
+    ops::Range,
+};
+
+use serde_json::json;
+
+use crate::{
+    edit::{self, Change},
+    matcher::find_iter,
+    replace_all, search_reader, Config, LineMatch, Matcher, SortBy,
+};
+
+/// Printed between groups of lines that aren't next to each other when showing context.
+const GROUP_SEPARATOR: &str = "--";
+
+// The same colors grep uses by default.
+const MATCH_COLOR: &str = "\x1b[1;31m";
+const PATH_COLOR: &str = "\x1b[35m";
+const LINE_NUMBER_COLOR: &str = "\x1b[32m";
+const SEPARATOR_COLOR: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+pub struct Printer<'c, W> {
+    config: &'c Config,
+    out: W,
+    color: bool,
+    /// Whether to put the name of the input in front of every line.
+    show_paths: bool,
+    /// Whether a group of context lines has been printed yet, so the next one knows it needs a
+    /// separator, even if it's in another file.
+    printed_group: bool,
+    /// With `--sort=score`, the output for each selected line so far, along with its distance.
+    /// None of it is written out until the end, when it can be sorted.
+    ranked: Option<Vec<(usize, Vec<u8>)>>,
+}
+
+impl<'c, W: Write> Printer<'c, W> {
+    /// Creates a printer writing to `out`, highlighting with terminal colors if `color` is true.
+    pub fn new(config: &'c Config, out: W, color: bool) -> Printer<'c, W> {
+        Printer {
+            config,
+            out,
+            color: color && !config.json,
+            show_paths: config.recursive || config.paths.len() > 1,
+            printed_group: false,
+            ranked: (config.sort_by == Some(SortBy::Score)).then(Vec::new),
+        }
+    }
+
+    /// Searches `reader` and prints the results. `name` is what the input is called in the
+    /// output.
+    ///
+    /// If `binary` is true, the lines of the input are garbage that shouldn't be dumped on the
+    /// terminal, so all that's printed is whether it matches (unless only counting or listing
+    /// files, which print the same as for text).
+    ///
+    /// # Errors
+    ///
+    /// Fails if reading from `reader` or writing the output fails.
+    pub fn print_matches(
+        &mut self,
+        matcher: &dyn Matcher,
+        name: &str,
+        reader: impl BufRead,
+        binary: bool,
+    ) -> io::Result<()> {
+        let config = self.config;
+        let mut selected = search_reader(matcher, reader).invert(config.invert_match);
+
+        if config.files_with_matches {
+            // No need to read any further than the first match.
+            if selected.next().transpose()?.is_some() {
+                self.paint(PATH_COLOR, name)?;
+                writeln!(self.out)?;
+            }
+
+            return Ok(());
+        } else if config.count {
+            let mut count = 0;
+            for line in selected {
+                line?;
+                count += 1;
+            }
+
+            if self.show_paths {
+                self.paint(PATH_COLOR, name)?;
+                self.paint(SEPARATOR_COLOR, ':')?;
+            }
+
+            return writeln!(self.out, "{count}");
+        } else if binary {
+            if selected.next().transpose()?.is_some() {
+                writeln!(self.out, "Binary file {name} matches")?;
+            }
+
+            return Ok(());
+        }
+
+        // Like grep, `-o` doesn't print context lines, as there's no match in them to print.
+        let showing_context =
+            !config.only_matching && (config.before_context > 0 || config.after_context > 0);
+        if showing_context {
+            selected = selected.context(config.before_context, config.after_context);
+        }
+
+        let mut last_line_number = None;
+        for line in selected {
+            let line = line?;
+            // Context and inverted lines don't match, so they have no distance to show.
+            let distance = match config.fuzzy {
+                Some(_) if !line.context && !config.invert_match => matcher.distance(&line.line),
+                _ => None,
+            };
+
+            if let Some(ranked) = &mut self.ranked {
+                let mut buffered = Printer {
+                    config,
+                    out: Vec::new(),
+                    color: self.color,
+                    show_paths: self.show_paths,
+                    printed_group: false,
+                    ranked: None,
+                };
+                buffered.print_line(matcher, name, &line, distance)?;
+                ranked.push((distance.unwrap_or_default(), buffered.out));
+                continue;
+            }
+
+            if showing_context && !config.json && last_line_number != Some(line.line_number - 1) {
+                if self.printed_group {
+                    self.paint(SEPARATOR_COLOR, GROUP_SEPARATOR)?;
+                    writeln!(self.out)?;
+                }
+
+                self.printed_group = true;
+            }
+
+            last_line_number = Some(line.line_number);
+            self.print_line(matcher, name, &line, distance)?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the changes the replacements make to an input as a diff, `contents` being what it
+    /// has now.
+    pub fn print_diff(
+        &mut self,
+        name: &str,
+        contents: &[u8],
+        changes: &[Change],
+    ) -> io::Result<()> {
+        edit::write_diff(&mut self.out, name, contents, changes)
+    }
+
+    /// Writes out everything `other` printed into its buffer, as if this printer had printed it.
+    pub fn append(&mut self, other: Printer<'_, Vec<u8>>) -> io::Result<()> {
+        // `other` doesn't know what was printed before it, so its first group of context lines
+        // is missing its separator.
+        if other.printed_group && self.printed_group {
+            self.paint(SEPARATOR_COLOR, GROUP_SEPARATOR)?;
+            writeln!(self.out)?;
+        }
+
+        self.printed_group |= other.printed_group;
+        if let (Some(ranked), Some(other_ranked)) = (&mut self.ranked, other.ranked) {
+            ranked.extend(other_ranked);
+        }
+
+        self.out.write_all(&other.out)
+    }
+
+    /// Writes out whatever was held back for sorting, flushes the output and returns it.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        if let Some(mut ranked) = self.ranked.take() {
+            // The sort is stable, so lines that are just as close stay in the order they were
+            // found.
+            ranked.sort_by_key(|&(distance, _)| distance);
+            for (_, output) in ranked {
+                self.out.write_all(&output)?;
+            }
+        }
+
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Prints a line the search returned, `distance` being how far it is from matching exactly
+    /// with `--fuzzy`.
+    fn print_line(
+        &mut self,
+        matcher: &dyn Matcher,
+        name: &str,
+        line: &LineMatch,
+        distance: Option<usize>,
+    ) -> io::Result<()> {
+        let config = self.config;
+        if config.json {
+            return self.print_json(matcher, name, line, distance);
+        }
+
+        if config.only_matching {
+            for range in matches(matcher, &line.line) {
+                self.print_prefix(name, line, distance)?;
+                match &config.replace {
+                    Some(replacement) => {
+                        let mut replaced = Vec::new();
+                        matcher.replace_match(&line.line, range, replacement, &mut replaced);
+                        self.paint(MATCH_COLOR, lossy(&replaced))?;
+                    }
+                    None => self.paint(MATCH_COLOR, lossy(&line.line[range]))?,
+                }
+                writeln!(self.out)?;
+            }
+
+            return Ok(());
+        }
+
+        self.print_prefix(name, line, distance)?;
+        if line.context || config.invert_match {
+            write!(self.out, "{}", lossy(&line.line))?;
+        } else if let Some(replacement) = &config.replace {
+            self.print_replaced(matcher, &line.line, replacement)?;
+        } else {
+            self.print_highlighted(matcher, &line.line)?;
+        }
+
+        writeln!(self.out)
+    }
+
+    fn print_prefix(
+        &mut self,
+        name: &str,
+        line: &LineMatch,
+        distance: Option<usize>,
+    ) -> io::Result<()> {
+        // Like grep, selected lines use `:` after the path and line number, and context lines use
+        // `-`.
+        let separator = if line.context { '-' } else { ':' };
+        if self.show_paths {
+            self.paint(PATH_COLOR, name)?;
+            self.paint(SEPARATOR_COLOR, separator)?;
+        }
+
+        if self.config.line_number {
+            self.paint(LINE_NUMBER_COLOR, line.line_number)?;
+            self.paint(SEPARATOR_COLOR, separator)?;
+        }
+
+        if let Some(distance) = distance {
+            self.paint(LINE_NUMBER_COLOR, format_args!("~{distance}"))?;
+            self.paint(SEPARATOR_COLOR, separator)?;
+        }
+
+        Ok(())
+    }
+
+    fn print_highlighted(&mut self, matcher: &dyn Matcher, line: &[u8]) -> io::Result<()> {
+        if !self.color {
+            return write!(self.out, "{}", lossy(line));
+        }
+
+        let mut written = 0;
+        for range in matches(matcher, line) {
+            write!(self.out, "{}", lossy(&line[written..range.start]))?;
+            self.paint(MATCH_COLOR, lossy(&line[range.clone()]))?;
+            written = range.end;
+        }
+
+        write!(self.out, "{}", lossy(&line[written..]))
+    }
+
+    /// Prints `line` with every match replaced (highlighting the replacements).
+    fn print_replaced(
+        &mut self,
+        matcher: &dyn Matcher,
+        line: &[u8],
+        replacement: &str,
+    ) -> io::Result<()> {
+        let mut written = 0;
+        let mut replaced = Vec::new();
+        // Unlike when highlighting, empty matches count here, since something can be put in
+        // their place (like a prefix for `^`).
+        for range in find_iter(matcher, line) {
+            write!(self.out, "{}", lossy(&line[written..range.start]))?;
+            written = range.end;
+            replaced.clear();
+            matcher.replace_match(line, range, replacement, &mut replaced);
+            self.paint(MATCH_COLOR, lossy(&replaced))?;
+        }
+
+        write!(self.out, "{}", lossy(&line[written..]))
+    }
+
+    /// Prints a line as a JSON object, on a line of its own.
+    fn print_json(
+        &mut self,
+        matcher: &dyn Matcher,
+        name: &str,
+        line: &LineMatch,
+        distance: Option<usize>,
+    ) -> io::Result<()> {
+        let submatches: Vec<_> = if line.context {
+            Vec::new()
+        } else {
+            matches(matcher, &line.line)
+                .map(|range| {
+                    json!({
+                        "match": lossy(&line.line[range.clone()]),
+                        "start": range.start,
+                        "end": range.end,
+                    })
+                })
+                .collect()
+        };
+
+        let mut value = json!({
+            "type": if line.context { "context" } else { "match" },
+            "path": name,
+            "line_number": line.line_number,
+            "byte_offset": line.byte_offset,
+            "line": lossy(&line.line),
+            "submatches": submatches,
+        });
+
+        match &self.config.replace {
+            Some(replacement) if !line.context => {
+                let replaced = replace_all(matcher, &line.line, replacement);
+                value["replacement"] = json!(lossy(&replaced));
+            }
+            _ => {}
+        }
+
+        if let Some(distance) = distance {
+            value["distance"] = json!(distance);
+        }
+
+        serde_json::to_writer(&mut self.out, &value)?;
+        writeln!(self.out)
+    }
+
+    fn paint(&mut self, color: &str, text: impl Display) -> io::Result<()> {
+        if self.color {
+            write!(self.out, "{color}{text}{RESET}")
+        } else {
+            write!(self.out, "{text}")
+        }
+    }
+}
+
+/// The ranges of the matches in `line` worth showing, which leaves out empty ones.
+fn matches<'a>(
+    matcher: &'a dyn Matcher,
+    line: &'a [u8],
+) -> impl Iterator<Item = Range<usize>> + 'a {
+    find_iter(matcher, line).filter(|range| !range.is_empty())
+}
+
+/// Lines are shown as text even when they aren't valid UTF-8, with the invalid bytes replaced by
+/// `�`. Offsets (like in the JSON output) still count the bytes of the original line.
+fn lossy(text: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorChoice, FuzzyMatcher, LiteralMatcher, RegexMatcher};
+
+    const CONTENTS: &str = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+    fn print(config: &Config, matcher: &dyn Matcher, color: bool) -> String {
+        let mut printer = Printer::new(config, Vec::new(), color);
+        printer.show_paths = true;
+        printer
+            .print_matches(matcher, "poem.txt", CONTENTS.as_bytes(), false)
+            .unwrap();
+        String::from_utf8(printer.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn plain_output() {
+        let config = Config {
+            line_number: true,
+            ..Config::default()
+        };
+        let matcher = LiteralMatcher::new("st");
+        assert_eq!(
+            print(&config, &matcher, false),
+            "poem.txt:1:Rust:\npoem.txt:2:safe, fast, productive.\npoem.txt:4:Trust me.\n"
+        );
+    }
+
+    #[test]
+    fn highlights_every_match() {
+        let config = Config {
+            color: ColorChoice::Always,
+            ..Config::default()
+        };
+        let matcher = RegexMatcher::new("[st]", false).unwrap();
+        let output = print(&config, &matcher, true);
+        let first_line = output.lines().next().unwrap();
+        assert_eq!(
+            first_line,
+            "\x1b[35mpoem.txt\x1b[0m\x1b[36m:\x1b[0mRu\x1b[1;31ms\x1b[0m\x1b[1;31mt\x1b[0m:"
+        );
+    }
+
+    #[test]
+    fn only_matching() {
+        let config = Config {
+            only_matching: true,
+            line_number: true,
+            after_context: 2,
+            ..Config::default()
+        };
+        let matcher = RegexMatcher::new(r"\w*st\w*", false).unwrap();
+        assert_eq!(
+            print(&config, &matcher, false),
+            "poem.txt:1:Rust\npoem.txt:2:fast\npoem.txt:4:Trust\n"
+        );
+
+        // Inverted lines have no matches to print.
+        let config = Config {
+            only_matching: true,
+            invert_match: true,
+            ..Config::default()
+        };
+        assert_eq!(print(&config, &matcher, false), "");
+    }
+
+    #[test]
+    fn json_output() {
+        let config = Config {
+            json: true,
+            ..Config::default()
+        };
+        let matcher = LiteralMatcher::new("e");
+        let output = print(&config, &matcher, true);
+        let values: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(values.len(), 3);
+        assert_eq!(
+            values[1],
+            json!({
+                "type": "match",
+                "path": "poem.txt",
+                "line_number": 3,
+                "byte_offset": 30,
+                "line": "Pick three.",
+                "submatches": [
+                    { "match": "e", "start": 8, "end": 9 },
+                    { "match": "e", "start": 9, "end": 10 },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn append_adds_missing_group_separators() {
+        let config = Config {
+            after_context: 1,
+            ..Config::default()
+        };
+        let matcher = LiteralMatcher::new("Pick");
+        let mut printer = Printer::new(&config, Vec::new(), false);
+        for _ in 0..2 {
+            let mut buffered = Printer::new(&config, Vec::new(), false);
+            buffered
+                .print_matches(&matcher, "poem.txt", CONTENTS.as_bytes(), false)
+                .unwrap();
+            printer.append(buffered).unwrap();
+        }
+
+        let output = String::from_utf8(printer.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            output,
+            "Pick three.\nTrust me.\n--\nPick three.\nTrust me.\n"
+        );
+    }
+
+    #[test]
+    fn binary_inputs_only_say_whether_they_match() {
+        let config = Config::default();
+        let matcher = LiteralMatcher::new("ELF");
+        let mut printer = Printer::new(&config, Vec::new(), false);
+        for name in ["a.out", "b.out"] {
+            printer
+                .print_matches(&matcher, name, &b"\x7fELF\0\nELF"[..], true)
+                .unwrap();
+        }
+        printer
+            .print_matches(&matcher, "c.out", &b"\0\0"[..], true)
+            .unwrap();
+
+        let output = String::from_utf8(printer.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            output,
+            "Binary file a.out matches\nBinary file b.out matches\n"
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_printed_lossily() {
+        let config = Config {
+            json: true,
+            ..Config::default()
+        };
+        let matcher = LiteralMatcher::new("b");
+        let mut printer = Printer::new(&config, Vec::new(), false);
+        printer
+            .print_matches(&matcher, "f", &b"a\xffb\n"[..], false)
+            .unwrap();
+
+        let output = printer.into_inner().unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(value["line"], "a\u{fffd}b");
+        assert_eq!(
+            value["submatches"][0],
+            json!({ "match": "b", "start": 2, "end": 3 })
+        );
+    }
+
+    #[test]
+    fn replaced_output() {
+        let config = Config {
+            replace: Some("<$0>".to_string()),
+            line_number: true,
+            ..Config::default()
+        };
+        let matcher = RegexMatcher::new(r"\bt\w+", true).unwrap();
+        assert_eq!(
+            print(&config, &matcher, false),
+            "poem.txt:3:Pick <three>.\npoem.txt:4:<Trust> me.\n"
+        );
+
+        let config = Config {
+            replace: Some("_".to_string()),
+            only_matching: true,
+            color: ColorChoice::Always,
+            ..Config::default()
+        };
+        let output = print(&config, &matcher, true);
+        assert_eq!(
+            output.lines().nth(1),
+            Some("\x1b[35mpoem.txt\x1b[0m\x1b[36m:\x1b[0m\x1b[1;31m_\x1b[0m")
+        );
+    }
+
+    #[test]
+    fn fuzzy_distances_and_ranking() {
+        let matcher = FuzzyMatcher::new("rust", 1, false);
+        let config = Config {
+            fuzzy: Some(1),
+            line_number: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            print(&config, &matcher, false),
+            "poem.txt:1:~1:Rust:\npoem.txt:4:~0:Trust me.\n"
+        );
+
+        let config = Config {
+            fuzzy: Some(1),
+            sort_by: Some(SortBy::Score),
+            json: true,
+            ..Config::default()
+        };
+        let matcher = FuzzyMatcher::new("three", 1, true);
+        let mut printer = Printer::new(&config, Vec::new(), false);
+        for name in ["a", "b"] {
+            let mut buffered = Printer::new(&config, Vec::new(), false);
+            buffered
+                .print_matches(&matcher, name, &b"thee\nthree\n"[..], false)
+                .unwrap();
+            printer.append(buffered).unwrap();
+        }
+
+        let output = printer.into_inner().unwrap();
+        let ranked: Vec<_> = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(&line.unwrap()).unwrap())
+            .map(|value| (value["path"].clone(), value["distance"].clone()))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                (json!("a"), json!(0)),
+                (json!("b"), json!(0)),
+                (json!("a"), json!(1)),
+                (json!("b"), json!(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn count_and_files_with_matches() {
+        let matcher = LiteralMatcher::new("st");
+        let config = Config {
+            count: true,
+            ..Config::default()
+        };
+        assert_eq!(print(&config, &matcher, false), "poem.txt:3\n");
+
+        let config = Config {
+            files_with_matches: true,
+            ..Config::default()
+        };
+        assert_eq!(print(&config, &matcher, false), "poem.txt\n");
+    }
+}
@@ -0,0 +1,376 @@
+//! Searching text as it's read, a chunk at a time, so memory use doesn't depend on the size of
+//! the input.
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, Read},
+    ops::Range,
+};
+
+use memchr::{memchr, memchr_iter, memrchr};
+
+use crate::{context::ContextWindow, Matcher};
+
+/// A line selected by a search, or shown as context for one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// The number of the line, starting from 1.
+    pub line_number: u64,
+    /// Where the line starts in the input, in bytes.
+    pub byte_offset: u64,
+    /// The line itself, without its line ending. It's whatever bytes the input had, which aren't
+    /// necessarily valid UTF-8.
+    pub line: Vec<u8>,
+    /// Whether the line is only here as context for a selected line near it.
+    pub context: bool,
+}
+
+/// How much of the input `SearchReader` reads at once when it can skip over lines that don't
+/// match.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Searches `reader` line by line, returning an iterator over the lines `matcher` matches.
+///
+/// Only a chunk of the input is kept in memory at a time (plus whatever `reader` buffers), so
+/// `matcher` can look for candidates in the whole chunk at once instead of checking every line.
+/// Inverting or showing context needs every line though, so those read one line at a time. Lines
+/// are split like `str::lines` does, so both `\n` and `\r\n` endings are removed.
+pub fn search_reader<R: BufRead>(matcher: &dyn Matcher, reader: R) -> SearchReader<'_, R> {
+    SearchReader {
+        matcher,
+        reader,
+        invert: false,
+        window: ContextWindow::new(0, 0),
+        pending: VecDeque::new(),
+        line_number: 0,
+        byte_offset: 0,
+        buf: Vec::new(),
+        chunk: Vec::new(),
+        chunk_pos: 0,
+        chunk_offset: 0,
+        done: false,
+    }
+}
+
+/// The iterator returned by `search_reader`.
+///
+/// If reading fails, the error is returned and the iteration ends.
+pub struct SearchReader<'m, R> {
+    matcher: &'m dyn Matcher,
+    reader: R,
+    invert: bool,
+    window: ContextWindow<LineMatch>,
+    /// Lines the window already emitted that haven't been returned yet.
+    pending: VecDeque<LineMatch>,
+    line_number: u64,
+    byte_offset: u64,
+    buf: Vec<u8>,
+    /// The input read so far that hasn't been searched yet starts at `chunk_pos` in `chunk`,
+    /// which always ends at the end of a line (or of the input).
+    chunk: Vec<u8>,
+    chunk_pos: usize,
+    /// Where `chunk` starts in the input, in bytes.
+    chunk_offset: u64,
+    done: bool,
+}
+
+impl<R> SearchReader<'_, R> {
+    /// Selects the lines that *don't* match instead.
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Also returns up to `before` lines before and `after` lines after each selected line, as
+    /// context. Lines are never returned twice, even if they're near several selected lines.
+    pub fn context(mut self, before: usize, after: usize) -> Self {
+        self.window = ContextWindow::new(before, after);
+        self
+    }
+}
+
+impl<R: BufRead> SearchReader<'_, R> {
+    fn next_line_by_line(&mut self) -> Option<io::Result<LineMatch>> {
+        while self.pending.is_empty() && !self.done {
+            self.buf.clear();
+            let read = match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+
+            self.line_number += 1;
+            let byte_offset = self.byte_offset;
+            self.byte_offset += read as u64;
+
+            let line = strip_line_ending(&self.buf);
+            let selected = self.matcher.is_match(line) != self.invert;
+            if !self.window.wants(selected) {
+                continue;
+            }
+
+            let line = LineMatch {
+                line_number: self.line_number,
+                byte_offset,
+                line: line.to_vec(),
+                context: false,
+            };
+
+            let pending = &mut self.pending;
+            self.window.push(line, selected, |mut line, context| {
+                line.context = context;
+                pending.push_back(line);
+            });
+        }
+
+        self.pending.pop_front().map(Ok)
+    }
+
+    fn next_in_chunks(&mut self) -> Option<io::Result<LineMatch>> {
+        loop {
+            if let Some((range, next)) =
+                next_matching_line(self.matcher, &self.chunk, self.chunk_pos)
+            {
+                let skipped = &self.chunk[self.chunk_pos..range.start];
+                self.line_number += memchr_iter(b'\n', skipped).count() as u64 + 1;
+                self.chunk_pos = next;
+                return Some(Ok(LineMatch {
+                    line_number: self.line_number,
+                    byte_offset: self.chunk_offset + range.start as u64,
+                    line: self.chunk[range].to_vec(),
+                    context: false,
+                }));
+            }
+
+            self.line_number += memchr_iter(b'\n', &self.chunk[self.chunk_pos..]).count() as u64;
+            if self.done {
+                return None;
+            }
+
+            if let Err(e) = self.read_chunk() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+
+    /// Replaces `chunk` with the next part of the input, setting `done` if there's nothing left.
+    fn read_chunk(&mut self) -> io::Result<()> {
+        self.chunk_offset += self.chunk.len() as u64;
+        self.chunk_pos = 0;
+        self.chunk.clear();
+
+        (&mut self.reader)
+            .take(CHUNK_LEN as u64)
+            .read_to_end(&mut self.chunk)?;
+        // Finish the last line, so no line is ever split between two chunks.
+        if self.chunk.last().is_some_and(|&byte| byte != b'\n') {
+            self.reader.read_until(b'\n', &mut self.chunk)?;
+        }
+
+        if self.chunk.is_empty() {
+            self.done = true;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for SearchReader<'_, R> {
+    type Item = io::Result<LineMatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Inverted searches select the lines that *don't* match, and context is made of those
+        // too, so skipping over them would only get in the way.
+        if self.invert || self.window.shows_context() {
+            self.next_line_by_line()
+        } else {
+            self.next_in_chunks()
+        }
+    }
+}
+
+impl<R: BufRead> std::iter::FusedIterator for SearchReader<'_, R> {}
+
+/// Finds the first line of `text` from `start` (which has to be where a line starts) that
+/// `matcher` matches, returning its range without the line ending, and where the line after it
+/// starts.
+///
+/// Rather than checking every line, this asks `matcher` for candidates in the whole text, and
+/// only checks the lines those land in.
+pub fn next_matching_line(
+    matcher: &dyn Matcher,
+    text: &[u8],
+    mut start: usize,
+) -> Option<(Range<usize>, usize)> {
+    while start < text.len() {
+        let candidate = matcher.find_candidate(text, start)?;
+        let line_start = memrchr(b'\n', &text[start..candidate]).map_or(start, |i| start + i + 1);
+        if line_start == text.len() {
+            // The candidate is past the last line ending, where there's no line at all.
+            return None;
+        }
+
+        let line_end = memchr(b'\n', &text[candidate..]).map_or(text.len(), |i| candidate + i);
+        let next = (line_end + 1).min(text.len());
+        let line = strip_line_ending(&text[line_start..line_end]);
+        if matcher.is_match(line) {
+            return Some((line_start..line_start + line.len(), next));
+        }
+
+        start = next;
+    }
+
+    None
+}
+
+fn strip_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LiteralMatcher;
+
+    const CONTENTS: &str = "\
+Rust:
+safe, fast, productive.\r
+Pick three.
+Trust me.";
+
+    fn lines(matches: impl Iterator<Item = io::Result<LineMatch>>) -> Vec<(u64, u64, String)> {
+        matches
+            .map(|m| m.unwrap())
+            .map(|m| {
+                (
+                    m.line_number,
+                    m.byte_offset,
+                    String::from_utf8(m.line).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_line_numbers_and_offsets() {
+        let matcher = LiteralMatcher::new("st");
+        let matches = search_reader(&matcher, CONTENTS.as_bytes());
+        assert_eq!(
+            lines(matches),
+            vec![
+                (1, 0, "Rust:".to_string()),
+                (2, 6, "safe, fast, productive.".to_string()),
+                (4, 43, "Trust me.".to_string()),
+            ]
+        );
+        assert_eq!(&CONTENTS[43..], "Trust me.");
+    }
+
+    #[test]
+    fn invert_selects_the_other_lines() {
+        let matcher = LiteralMatcher::new("st");
+        let matches = search_reader(&matcher, CONTENTS.as_bytes()).invert(true);
+        assert_eq!(lines(matches), vec![(3, 31, "Pick three.".to_string())]);
+    }
+
+    #[test]
+    fn agrees_with_in_memory_search() {
+        let matcher = LiteralMatcher::new("e");
+        let streamed: Vec<_> = search_reader(&matcher, CONTENTS.as_bytes())
+            .map(|m| m.unwrap().line)
+            .collect();
+        let in_memory = crate::search_with(&matcher, CONTENTS);
+        assert_eq!(
+            streamed,
+            in_memory
+                .iter()
+                .map(|line| line.as_bytes())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn searches_past_invalid_utf8() {
+        let contents = &b"a\n\xff\n\xfea\xfe\r\nb\n"[..];
+        let matcher = LiteralMatcher::new("a");
+        let matches: Vec<_> = search_reader(&matcher, contents)
+            .map(|m| m.unwrap())
+            .map(|m| (m.line_number, m.byte_offset, m.line))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![(1, 0, b"a".to_vec()), (3, 4, b"\xfea\xfe".to_vec())]
+        );
+
+        let inverted: Vec<_> = search_reader(&matcher, contents)
+            .invert(true)
+            .map(|m| m.unwrap().line)
+            .collect();
+        assert_eq!(inverted, vec![b"\xff".to_vec(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn context_lines() {
+        let contents = "a\nb\nmatch\nc\nd\ne\nf\nmatch\ng\n";
+        let matcher = LiteralMatcher::new("match");
+        let matches: Vec<_> = search_reader(&matcher, contents.as_bytes())
+            .context(1, 2)
+            .map(|m| m.unwrap())
+            .map(|m| (m.line_number, m.context))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![
+                (2, true),
+                (3, false),
+                (4, true),
+                (5, true),
+                (7, true),
+                (8, false),
+                (9, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn agrees_with_line_by_line_search_across_chunks() {
+        let mut contents = String::new();
+        for i in 0..20_000 {
+            contents.push_str(&format!("line {i}, {}\r\n", "-".repeat(i % 7)));
+        }
+
+        let matcher = LiteralMatcher::new("7, ");
+        let chunked = lines(search_reader(&matcher, contents.as_bytes()));
+        // Asking for context makes the reader go line by line.
+        let everything =
+            lines(search_reader(&LiteralMatcher::new(""), contents.as_bytes()).context(0, 1));
+        let expected: Vec<_> = everything
+            .into_iter()
+            .filter(|(_, _, line)| line.contains("7, "))
+            .collect();
+
+        assert!(contents.len() > 4 * CHUNK_LEN);
+        assert_eq!(chunked.len(), 2000);
+        assert_eq!(chunked, expected);
+    }
+
+    #[test]
+    fn reads_lines_in_small_chunks() {
+        // A reader whose buffer is smaller than a line still produces whole lines.
+        let reader = io::BufReader::with_capacity(2, CONTENTS.as_bytes());
+        let matcher = LiteralMatcher::new("three");
+        assert_eq!(
+            lines(search_reader(&matcher, reader)),
+            vec![(3, 31, "Pick three.".to_string())]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Finding the files to search when running with `-r`.
+
+use std::path::{Path, PathBuf};
+
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+
+/// How many bytes at the start of a file `is_binary` looks at.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+/// Returns every file under `root`, skipping whatever `.gitignore`/`.ignore` files (and hidden
+/// files) say to skip.
+///
+/// If there are any `include` globs, only files matching at least one of them are returned. Files
+/// matching an `exclude` glob never are. Globs without a `/` match the file name anywhere in the
+/// tree, like in a `.gitignore`.
+///
+/// If `sorted` is true, the files come out sorted by path, otherwise they come in whatever order
+/// the file system lists them.
+///
+/// Symbolic links are never followed, so loops in the tree can't make us walk forever. Errors
+/// found while walking (like directories we can't read) are returned along with the files.
+///
+/// # Errors
+///
+/// Fails if one of the globs is invalid.
+pub fn files(
+    root: &Path,
+    include: &[String],
+    exclude: &[String],
+    sorted: bool,
+) -> Result<impl Iterator<Item = Result<PathBuf, ignore::Error>>, ignore::Error> {
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in include {
+        overrides.add(glob)?;
+    }
+
+    // In an override, a leading `!` means "ignore the files that match".
+    for glob in exclude {
+        overrides.add(&format!("!{glob}"))?;
+    }
+
+    let mut walk = WalkBuilder::new(root);
+    walk.overrides(overrides.build()?)
+        .follow_links(false)
+        // Honour `.gitignore`s even when the tree isn't inside a git repository.
+        .require_git(false);
+
+    if sorted {
+        walk.sort_by_file_name(|a, b| a.cmp(b));
+    }
+
+    let files = walk.build().filter_map(|entry| match entry {
+        Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => Some(Ok(entry.into_path())),
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    });
+
+    Ok(files)
+}
+
+/// Guesses whether `contents` is a binary file, the same way grep does: by looking for a NUL byte
+/// near the start, which text files practically never have.
+pub fn is_binary(contents: &[u8]) -> bool {
+    contents[..contents.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn found(root: &Path, include: &[&str], exclude: &[&str]) -> Vec<String> {
+        let include: Vec<_> = include.iter().map(|s| s.to_string()).collect();
+        let exclude: Vec<_> = exclude.iter().map(|s| s.to_string()).collect();
+        let mut paths: Vec<_> = files(root, &include, &exclude, false)
+            .unwrap()
+            .map(|path| {
+                let path = path.unwrap();
+                let path = path.strip_prefix(root).unwrap().to_str().unwrap();
+                path.replace('\\', "/")
+            })
+            .collect();
+
+        paths.sort();
+        paths
+    }
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("src/.ignore"), "*.log\n").unwrap();
+        fs::write(root.join("README.md"), "hello").unwrap();
+        fs::write(root.join("src/main.rs"), "hello").unwrap();
+        fs::write(root.join("src/debug.log"), "hello").unwrap();
+        fs::write(root.join("src/nested/lib.rs"), "hello").unwrap();
+        fs::write(root.join("src/nested/notes.txt"), "hello").unwrap();
+        fs::write(root.join("target/out.rs"), "hello").unwrap();
+        dir
+    }
+
+    #[test]
+    fn honours_ignore_files() {
+        let dir = tree();
+        assert_eq!(
+            found(dir.path(), &[], &[]),
+            vec![
+                "README.md",
+                "src/main.rs",
+                "src/nested/lib.rs",
+                "src/nested/notes.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        let dir = tree();
+        assert_eq!(
+            found(dir.path(), &["*.rs"], &[]),
+            vec!["src/main.rs", "src/nested/lib.rs"]
+        );
+        assert_eq!(
+            found(dir.path(), &["*.rs", "*.md"], &["main.rs"]),
+            vec!["README.md", "src/nested/lib.rs"]
+        );
+        assert_eq!(
+            found(dir.path(), &[], &["nested"]),
+            vec!["README.md", "src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn invalid_glob_is_an_error() {
+        let dir = tree();
+        assert!(files(dir.path(), &["a[".to_string()], &[], false).is_err());
+    }
+
+    #[test]
+    fn sorted_walk() {
+        let dir = tree();
+        let paths: Vec<_> = files(dir.path(), &[], &[], true)
+            .unwrap()
+            .map(|path| path.unwrap())
+            .collect();
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(paths, sorted);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn does_not_follow_symlink_loops() {
+        let dir = tree();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("src/loop")).unwrap();
+        assert_eq!(found(dir.path(), &["*.rs"], &[]).len(), 2);
+    }
+
+    #[test]
+    fn sniffs_binary_files() {
+        assert!(!is_binary(b"plain text\n"));
+        assert!(is_binary(b"\x7fELF\x02\x01\x01\0\0"));
+
+        let mut late_nul = vec![b'a'; BINARY_SNIFF_LEN];
+        late_nul.push(0);
+        assert!(!is_binary(&late_nul));
+    }
+}